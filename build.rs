@@ -0,0 +1,5 @@
+fn main() {
+    // Only the gRPC server needs the protobuf bindings, and generating them needs `protoc`.
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/bridge.proto").expect("Cannot compile proto/bridge.proto");
+}
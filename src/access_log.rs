@@ -0,0 +1,80 @@
+//! Optional access log in the NCSA Combined Log Format, for log analytics that expect it rather than the structured
+//! tracing output.
+//!
+//! Lines go to stderr so they never mix with the JSON logs on stdout.
+
+use axum::body::HttpBody;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::{CONTENT_LENGTH, REFERER, USER_AGENT};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::Utc;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+/// Writes one line per request once its response head is ready, the request duration in microseconds appended to the
+/// Combined Log Format fields.
+pub async fn log(State(enabled): State<bool>, request: Request, next: Next) -> Response {
+    if !enabled {
+        return next.run(request).await;
+    }
+    let received_at = Utc::now();
+    let started_at = Instant::now();
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or("-".to_owned(), |ConnectInfo(peer)| peer.ip().to_string());
+    let request_line = format!(
+        "{} {} {:?}",
+        request.method(),
+        request
+            .uri()
+            .path_and_query()
+            .map_or("/", |path| path.as_str()),
+        request.version()
+    );
+    let referer = header(request.headers(), REFERER.as_str());
+    let user_agent = header(request.headers(), USER_AGENT.as_str());
+
+    let response = next.run(request).await;
+
+    // The size is the one before compression, and streamed bodies have none known up front.
+    let bytes = header(response.headers(), CONTENT_LENGTH.as_str())
+        .or_else(|| {
+            response
+                .body()
+                .size_hint()
+                .exact()
+                .map(|size| size.to_string())
+        })
+        .unwrap_or("-".to_owned());
+    let line = format!(
+        "{} - - [{}] \"{}\" {} {} \"{}\" \"{}\" {}\n",
+        client,
+        received_at.format("%d/%b/%Y:%H:%M:%S %z"),
+        escape(&request_line),
+        response.status().as_u16(),
+        bytes,
+        escape(referer.as_deref().unwrap_or("-")),
+        escape(user_agent.as_deref().unwrap_or("-")),
+        started_at.elapsed().as_micros()
+    );
+    if let Err(err) = std::io::stderr().write_all(line.as_bytes()) {
+        tracing::warn!("⚠️ Cannot write the access log: {:?}", err);
+    }
+    response
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// Escapes quotes and backslashes so client-supplied values can't break out of their quoted field.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
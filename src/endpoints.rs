@@ -0,0 +1,46 @@
+//! Catalog entries of the registered routes, served on `/endpoints` for service discovery without OpenAPI.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+pub struct Endpoint {
+    pub path: &'static str,
+    pub query_params: &'static [&'static str],
+    pub description: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CatalogEntry<'a> {
+    path: &'a str,
+    methods: [&'a str; 2],
+    path_params: Vec<&'a str>,
+    query_params: &'a [&'a str],
+    description: &'a str,
+}
+
+pub const ENDPOINTS: Endpoint = Endpoint {
+    path: "/endpoints",
+    query_params: &[],
+    description: "Lists the available endpoints",
+};
+
+/// Describes the endpoints, reading their path parameters off the `:name` segments of their paths.
+pub fn catalog<'a>(endpoints: impl IntoIterator<Item = &'a Endpoint>) -> Value {
+    let entries: Vec<CatalogEntry> = endpoints
+        .into_iter()
+        .map(|endpoint| CatalogEntry {
+            path: endpoint.path,
+            // Every route is registered with `get`, which also answers `HEAD`.
+            methods: ["GET", "HEAD"],
+            path_params: endpoint
+                .path
+                .split('/')
+                .filter_map(|segment| segment.strip_prefix(':'))
+                .collect(),
+            query_params: endpoint.query_params,
+            description: endpoint.description,
+        })
+        .collect();
+    json!({ "endpoints": entries })
+}
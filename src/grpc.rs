@@ -0,0 +1,274 @@
+//! Optional gRPC server for typed internal clients, answering through the same functions as the REST endpoints so both
+//! share the caches, limits and upstream calls, behind the same maintenance mode, endpoint timeouts and input limits.
+//! Built with the `grpc` feature and served when `GRPC_PORT` is set.
+
+use crate::{
+    avl_head, avl_proof, eth_proof, ActiveRequest, AppState, AvlProofQuery, EndpointError,
+    EndpointTimeouts, IndexStruct, Maintenance, NodeEncoding, ProofFormat,
+};
+use alloy_primitives::{B256, U256};
+use axum::http::StatusCode;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status};
+
+mod proto {
+    tonic::include_proto!("bridge");
+}
+
+use proto::bridge_server::{Bridge, BridgeServer};
+
+struct BridgeService {
+    state: Arc<AppState>,
+    maintenance: Option<Maintenance>,
+    timeouts: EndpointTimeouts,
+}
+
+impl BridgeService {
+    /// Runs a call the way the REST data endpoints run their handlers: refused in maintenance, counted as an active
+    /// request and cut off after the endpoint's own timeout.
+    async fn guarded<T>(
+        &self,
+        timeout: Option<Duration>,
+        call: impl Future<Output = Result<T, Status>>,
+    ) -> Result<Response<T>, Status> {
+        if let Some(maintenance) = &self.maintenance {
+            return Err(Status::unavailable(maintenance.message.clone()));
+        }
+        let _active_request = ActiveRequest::start(&self.state.active_requests);
+        let Some(timeout) = timeout else {
+            return call.await.map(Response::new);
+        };
+        match tokio::time::timeout(timeout, call).await {
+            Ok(response) => response.map(Response::new),
+            Err(_) => {
+                tracing::warn!("⏳ Request timed out after {:?}", timeout);
+                Err(Status::deadline_exceeded(format!(
+                    "Request timed out after {}ms",
+                    timeout.as_millis()
+                )))
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Bridge for BridgeService {
+    async fn get_eth_proof(
+        &self,
+        request: Request<proto::GetEthProofRequest>,
+    ) -> Result<Response<proto::EthProof>, Status> {
+        let request = request.into_inner();
+        self.guarded(self.timeouts.eth_proof, async {
+            let block_hash = hash("block_hash", &request.block_hash)?;
+            let index_struct = IndexStruct {
+                index: request.index,
+                format: ProofFormat::Json,
+                wait: false,
+                partial: false,
+                fresh: false,
+                timeout: None,
+                range_hash: None,
+            };
+            let (proof, _) = eth_proof(&self.state, block_hash, &index_struct, false).await?;
+            let proof = proof.with_directions();
+            Ok(proto::EthProof {
+                data_root_proof: to_vecs(proof.data_root_proof),
+                data_root_proof_directions: proof.data_root_proof_directions,
+                leaf_proof: to_vecs(proof.leaf_proof),
+                leaf_proof_directions: proof.leaf_proof_directions,
+                range_hash: proof.range_hash.to_vec(),
+                data_root_index: proof.data_root_index.into(),
+                leaf: proof.leaf.to_vec(),
+                leaf_index: proof.leaf_index,
+                data_root: proof.data_root.to_vec(),
+                blob_root: proof.blob_root.to_vec(),
+                bridge_root: proof.bridge_root.to_vec(),
+                data_root_commitment: proof.data_root_commitment.to_vec(),
+                block_hash: proof.block_hash.to_vec(),
+                block_number: proof.block_number,
+                message_json: proof
+                    .message
+                    .map(|message| serde_json::to_string(&message))
+                    .transpose()
+                    .map_err(|err| Status::internal(err.to_string()))?,
+            })
+        })
+        .await
+    }
+
+    async fn get_avl_proof(
+        &self,
+        request: Request<proto::GetAvlProofRequest>,
+    ) -> Result<Response<proto::AvlProof>, Status> {
+        let request = request.into_inner();
+        self.guarded(self.timeouts.avl_proof, async {
+            let block_hash = hash("block_hash", &request.block_hash)?;
+            let message_id = U256::try_from_be_slice(&request.message_id)
+                .ok_or_else(|| Status::invalid_argument("message_id must fit in 32 bytes"))?;
+            let avl_proof_query = AvlProofQuery {
+                slot_offset: request.slot_offset,
+                slots: request.slots,
+                min_confirmations: request.min_confirmations,
+                encoding: NodeEncoding::Hex,
+            };
+            let proofs = avl_proof(&self.state, block_hash, message_id, &avl_proof_query).await?;
+            Ok(proto::AvlProof {
+                account_proof: proofs
+                    .account_proof
+                    .into_iter()
+                    .map(|node| node.to_vec())
+                    .collect(),
+                storage_proofs: proofs
+                    .storage_proofs
+                    .into_iter()
+                    .map(|storage_proof| proto::StorageProof {
+                        key: storage_proof.key.to_vec(),
+                        proof: storage_proof
+                            .proof
+                            .into_iter()
+                            .map(|node| node.to_vec())
+                            .collect(),
+                    })
+                    .collect(),
+            })
+        })
+        .await
+    }
+
+    async fn get_head(
+        &self,
+        _request: Request<proto::GetHeadRequest>,
+    ) -> Result<Response<proto::Head>, Status> {
+        self.guarded(self.timeouts.head, async {
+            let head = avl_head(&self.state).await?;
+            Ok(proto::Head {
+                start: head.data.start,
+                end: head.data.end,
+                commitment: head.commitment.map(|commitment| proto::Commitment {
+                    ethereum_block_number: commitment.ethereum_block_number,
+                    transaction_hash: commitment.transaction_hash.to_vec(),
+                }),
+            })
+        })
+        .await
+    }
+}
+
+/// Serves the gRPC interface on `listener` until `shutdown` is cancelled, letting calls in progress finish. Requests
+/// are capped at `MAX_URI_LENGTH` bytes, as REST requests are.
+pub async fn serve(
+    state: Arc<AppState>,
+    maintenance: Option<Maintenance>,
+    timeouts: EndpointTimeouts,
+    listener: TcpListener,
+    shutdown: CancellationToken,
+) {
+    let max_request_size = state.max_uri_length;
+    let service = BridgeServer::new(BridgeService {
+        state,
+        maintenance,
+        timeouts,
+    })
+    .max_decoding_message_size(max_request_size);
+    if let Err(err) = tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown.cancelled())
+        .await
+    {
+        tracing::error!("❌ gRPC server failed: {:?}", err);
+    }
+}
+
+// `Status` is large, but it is what the service methods return anyway.
+#[allow(clippy::result_large_err)]
+fn hash(field: &str, bytes: &[u8]) -> Result<B256, Status> {
+    B256::try_from(bytes)
+        .map_err(|_| Status::invalid_argument(format!("{} must be 32 bytes", field)))
+}
+
+fn to_vecs(hashes: Vec<B256>) -> Vec<Vec<u8>> {
+    hashes.into_iter().map(|hash| hash.to_vec()).collect()
+}
+
+/// Turns an endpoint error into the closest gRPC status.
+impl From<EndpointError> for Status {
+    fn from(err: EndpointError) -> Self {
+        let message = err.message();
+        match err.status {
+            StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+            StatusCode::NOT_FOUND => Status::not_found(message),
+            StatusCode::TOO_EARLY => Status::failed_precondition(message),
+            StatusCode::TOO_MANY_REQUESTS => Status::resource_exhausted(message),
+            StatusCode::SERVICE_UNAVAILABLE => Status::unavailable(message),
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => {
+                Status::deadline_exceeded(message)
+            }
+            _ => Status::internal(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_state;
+    use std::sync::atomic::Ordering;
+
+    async fn service(
+        maintenance: Option<Maintenance>,
+        timeouts: EndpointTimeouts,
+    ) -> BridgeService {
+        BridgeService {
+            state: Arc::new(build_state("local").await.unwrap()),
+            maintenance,
+            timeouts,
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_are_unavailable_in_maintenance() {
+        let maintenance = Maintenance {
+            message: "Down for the test".to_owned(),
+            retry_after: "300".to_owned(),
+        };
+        let service = service(Some(maintenance), EndpointTimeouts::default()).await;
+        let status = service
+            .get_head(Request::new(proto::GetHeadRequest {}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(status.message(), "Down for the test");
+    }
+
+    #[tokio::test]
+    async fn calls_time_out_like_their_endpoint() {
+        // Accepts connections and never answers, so the range request hangs.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+        let timeouts = EndpointTimeouts {
+            head: Some(Duration::from_millis(50)),
+            ..EndpointTimeouts::default()
+        };
+        let mut service = service(None, timeouts).await;
+        Arc::get_mut(&mut service.state).unwrap().succinct_base_url = upstream;
+
+        let status = service
+            .get_head(Request::new(proto::GetHeadRequest {}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+        assert_eq!(status.message(), "Request timed out after 50ms");
+        assert_eq!(service.state.active_requests.load(Ordering::Relaxed), 0);
+    }
+}
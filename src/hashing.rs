@@ -0,0 +1,90 @@
+//! Hash primitive of the Avail data trees, selectable so a bridge upgrade to another hash is a config change.
+
+use alloy_primitives::B256;
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+use sp_io::hashing::{blake2_256, sha2_256};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Keccak256,
+    Sha256,
+    Blake2,
+}
+
+impl HashAlgorithm {
+    pub fn hash(self, data: &[u8]) -> B256 {
+        match self {
+            HashAlgorithm::Keccak256 => B256::from_slice(&Keccak256::digest(data)),
+            HashAlgorithm::Sha256 => B256::from(sha2_256(data)),
+            HashAlgorithm::Blake2 => B256::from(blake2_256(data)),
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "keccak256" => Ok(HashAlgorithm::Keccak256),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "blake2" => Ok(HashAlgorithm::Blake2),
+            _ => Err(format!("Unknown hash algorithm {}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Published test vectors for the empty message and "abc".
+
+    #[test]
+    fn keccak256() {
+        assert_eq!(
+            HashAlgorithm::Keccak256.hash(b"").to_string(),
+            "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            HashAlgorithm::Keccak256.hash(b"abc").to_string(),
+            "0x4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+
+    #[test]
+    fn sha256() {
+        assert_eq!(
+            HashAlgorithm::Sha256.hash(b"").to_string(),
+            "0xe3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            HashAlgorithm::Sha256.hash(b"abc").to_string(),
+            "0xba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn blake2() {
+        assert_eq!(
+            HashAlgorithm::Blake2.hash(b"").to_string(),
+            "0x0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+        assert_eq!(
+            HashAlgorithm::Blake2.hash(b"abc").to_string(),
+            "0xbddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319"
+        );
+    }
+
+    #[test]
+    fn parses_names_case_insensitively() {
+        assert_eq!("Keccak256".parse(), Ok(HashAlgorithm::Keccak256));
+        assert_eq!("sha256".parse(), Ok(HashAlgorithm::Sha256));
+        assert_eq!("BLAKE2".parse(), Ok(HashAlgorithm::Blake2));
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! Rollup of the per-dependency health into a single status, in the shape a public status page polls.
+
+use serde::Serialize;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Operational,
+    Degraded,
+    Down,
+}
+
+/// Head lags past which the bridge is reported degraded, then down.
+pub struct HealthThresholds {
+    pub degraded_lag: Duration,
+    pub down_lag: Duration,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Components {
+    /// Whether the Vector pallet head could be read from Avail.
+    pub avail: Health,
+    /// Whether the Succinct range API answered with a committed range.
+    pub succinct: Health,
+    /// Degraded when the range API and the VectorX contract disagree or couldn't be compared.
+    pub range_reconciliation: Health,
+    /// Degraded when retries are being skipped for lack of budget.
+    pub retry_budget: Health,
+}
+
+impl HealthThresholds {
+    /// Health of the bridge given how far the Ethereum head stored on Avail lags behind, `None` if it is unknown.
+    pub fn lag(&self, lag: Option<Duration>) -> Health {
+        match lag {
+            Some(lag) if lag > self.down_lag => Health::Down,
+            Some(lag) if lag > self.degraded_lag => Health::Degraded,
+            Some(_) => Health::Operational,
+            None => Health::Degraded,
+        }
+    }
+}
+
+impl Components {
+    /// Overall health: down when the bridge has stalled past the down threshold or neither Avail nor the range API
+    /// answer, since no proof can be served then, otherwise the worst of the components and the lag.
+    pub fn rollup(&self, lag: Health) -> Health {
+        if self.avail == Health::Down && self.succinct == Health::Down {
+            return Health::Down;
+        }
+        let worst_component = [
+            self.avail,
+            self.succinct,
+            self.range_reconciliation,
+            self.retry_budget,
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(Health::Operational)
+        .min(Health::Degraded);
+        worst_component.max(lag)
+    }
+}
@@ -0,0 +1,116 @@
+//! Self-contained inclusion bundle of an aggregated proof, spelling out which side each sibling hashes on so clients
+//! can verify it without knowing how the bridge contract walks the trees.
+
+use crate::hashing::HashAlgorithm;
+use crate::AggregatedResponse;
+use alloy_primitives::B256;
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionBundle {
+    pub block_hash: B256,
+    pub leaf: B256,
+    /// Steps from the leaf up to the data root commitment, each starting from the previous step's root.
+    pub steps: Vec<InclusionStep>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionStep {
+    pub tree: &'static str,
+    pub hash_algorithm: HashAlgorithm,
+    pub node: B256,
+    pub index: u32,
+    pub path: Vec<PathNode>,
+    pub root: B256,
+}
+
+#[derive(Serialize)]
+pub struct PathNode {
+    pub sibling: B256,
+    pub position: SiblingPosition,
+}
+
+/// Side of the sibling when hashing it with the current node, `Left` meaning `hash(sibling ++ node)`.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SiblingPosition {
+    Left,
+    Right,
+}
+
+impl InclusionBundle {
+    /// Follows the bridge contract: the leaf is hashed once more before its proof is checked against the bridge
+    /// root for messages or the blob root otherwise, `dataRoot = hash(blobRoot ++ bridgeRoot)`, all with the Avail
+    /// trees' `hash_algorithm`, and the data root is proven against the commitment with SHA-256.
+    pub fn new(proof: AggregatedResponse, hash_algorithm: HashAlgorithm) -> Self {
+        let (leaf_root, data_root_step) = if proof.message.is_some() {
+            (
+                proof.bridge_root,
+                PathNode {
+                    sibling: proof.blob_root,
+                    position: SiblingPosition::Left,
+                },
+            )
+        } else {
+            (
+                proof.blob_root,
+                PathNode {
+                    sibling: proof.bridge_root,
+                    position: SiblingPosition::Right,
+                },
+            )
+        };
+        let data_root_index = match data_root_step.position {
+            SiblingPosition::Left => 1,
+            SiblingPosition::Right => 0,
+        };
+        InclusionBundle {
+            block_hash: proof.block_hash,
+            leaf: proof.leaf,
+            steps: vec![
+                InclusionStep {
+                    tree: "leaf",
+                    hash_algorithm,
+                    node: hash_algorithm.hash(proof.leaf.as_slice()),
+                    index: proof.leaf_index,
+                    path: path(proof.leaf_index, proof.leaf_proof),
+                    root: leaf_root,
+                },
+                InclusionStep {
+                    tree: "dataRoot",
+                    hash_algorithm,
+                    node: leaf_root,
+                    index: data_root_index,
+                    path: vec![data_root_step],
+                    root: proof.data_root,
+                },
+                InclusionStep {
+                    tree: "dataRootCommitment",
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    node: proof.data_root,
+                    index: u32::from(proof.data_root_index),
+                    path: path(u32::from(proof.data_root_index), proof.data_root_proof),
+                    root: proof.data_root_commitment,
+                },
+            ],
+        }
+    }
+}
+
+/// Pairs each sibling with its side, the `n`th bit of the index being set when the node is a right child at level `n`.
+fn path(index: u32, siblings: Vec<B256>) -> Vec<PathNode> {
+    siblings
+        .into_iter()
+        .enumerate()
+        .map(|(level, sibling)| PathNode {
+            sibling,
+            position: if index.checked_shr(level as u32).unwrap_or(0) & 1 == 1 {
+                SiblingPosition::Left
+            } else {
+                SiblingPosition::Right
+            },
+        })
+        .collect()
+}
@@ -1,57 +1,398 @@
-use alloy_primitives::{hex, B256, U256};
+mod access_log;
+mod endpoints;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod hashing;
+mod health;
+mod inclusion_bundle;
+mod proof_store;
+mod proof_verification;
+mod range_reconciliation;
+mod redact;
+mod retry;
+mod retry_budget;
+mod rpc_proxy;
+mod server;
+mod smoke_test;
+mod startup;
+mod storage_key;
+mod storage_layout;
+#[cfg(unix)]
+mod uds;
+mod upstream;
+mod zstd_dictionary;
+
+use alloy_primitives::{hex, Bytes, B256, U256, U64};
+use alloy_sol_types::{sol, SolEvent, SolValue};
 use avail_core::data_proof::AddressedMessage;
 use axum::{
-    extract::{Json, Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::get,
+    async_trait,
+    body::Body,
+    extract::{FromRequestParts, Json, Path, Query, Request, State},
+    http::{
+        header::{ACCEPT, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, VARY},
+        request::Parts,
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, MethodRouter},
     Router,
 };
 use chrono::Utc;
+use endpoints::Endpoint;
+use futures::future::join_all;
+use hashing::HashAlgorithm;
+use health::{Components, Health, HealthThresholds};
 use http::Method;
+use inclusion_bundle::InclusionBundle;
 use jsonrpsee::core::Error;
-use jsonrpsee::{
-    core::client::ClientT,
-    http_client::{HttpClient, HttpClientBuilder},
-    rpc_params,
-};
-use reqwest::Client;
+use jsonrpsee::{core::client::ClientT, rpc_params};
+use proof_store::ProofStore;
+use range_reconciliation::RangeReconciliation;
+use redact::{redact, Redacting};
+use reqwest::{redirect, Client};
+use retry::{retry, Idempotent, RetryPolicy};
+use retry_budget::RetryBudget;
+use rpc_proxy::{RpcClient, RpcProxyLayer};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use sha3::{Digest, Keccak256};
+use server::ServerConfig;
 use sp_core::Decode;
-use sp_io::hashing::twox_128;
+use startup::{env_or, rpc_client, StartupError};
+use std::collections::HashMap;
 use std::env;
-use std::sync::Arc;
+use std::fmt::Display;
+use std::io;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use storage_key::StorageHasher;
+use storage_layout::StorageLayout;
 #[cfg(not(target_env = "msvc"))]
 use tikv_jemallocator::Jemalloc;
 use tokio::join;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tower::Layer;
 use tower_http::{
-    compression::CompressionLayer,
+    compression::{
+        predicate::{DefaultPredicate, Predicate, SizeAbove},
+        CompressionLayer,
+    },
     cors::{Any, CorsLayer},
+    limit::RequestBodyLimitLayer,
+    normalize_path::NormalizePathLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     trace::TraceLayer,
 };
 use tracing_subscriber::prelude::*;
+use zstd_dictionary::ZstdDictionary;
 
 #[cfg(not(target_env = "msvc"))]
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const BEACON_PROVIDER_HEADER: &str = "x-beacon-provider";
+const FIELD_CASE_HEADER: &str = "x-field-case";
+const PROBLEM_JSON: &str = "application/problem+json";
+const PROBLEM_TYPE_PREFIX: &str = "urn:bridge-api:problem:";
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 64;
+/// Longest path segment or query value accepted, comfortably above a decimal `U256` or a hex `B256`.
+const MAX_INPUT_LENGTH: usize = 128;
+const SLOTS_PER_EPOCH: u32 = 32;
+const MAX_MULTI_INDICES: usize = 64;
+/// Seconds a client shed from `eth_getProof` should wait before retrying.
+const GET_PROOF_RETRY_AFTER: &str = "1";
+/// Fields of [`VerboseSlotMappingResponse`] that can be selected with `?fields=`.
+const BEACON_SLOT_FIELDS: [&str; 6] = [
+    "blockHash",
+    "blockNumber",
+    "blockRoot",
+    "epoch",
+    "slot",
+    "execStateRoot",
+];
+const BRIDGED_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Widest block range of the commitment's `eth_getLogs`, the most many providers accept in one call.
+const MAX_COMMITMENT_LOOKBACK_BLOCKS: u64 = 10_000;
+
 struct AppState {
-    avail_client: HttpClient,
-    ethereum_client: HttpClient,
-    request_client: Client,
+    name: String,
+    network: Option<String>,
+    avail_client: RpcClient,
+    avail_data_proof_client: Option<RpcClient>,
+    ethereum_client: RpcClient,
+    succinct_client: Client,
+    beacon_client: Client,
     succinct_base_url: String,
     beaconchain_base_url: String,
+    beacon_node_url: Option<String>,
+    beacon_genesis_time: Option<u64>,
+    seconds_per_slot: NonZeroU64,
+    beacon_max_attempts: NonZeroU32,
     avail_chain_name: String,
     contract_chain_id: String,
     contract_address: String,
     bridge_contract_address: String,
+    max_message_id: U256,
+    avail_permits: UpstreamPermits,
+    ethereum_permits: UpstreamPermits,
+    succinct_permits: UpstreamPermits,
+    beaconchain_permits: UpstreamPermits,
+    get_proof_permits: UpstreamPermits,
+    get_proof_permit_wait: Duration,
+    /// Timeout of the `eth_getProof` call alone, `None` leaving it to the Ethereum client's own.
+    get_proof_timeout: Option<Duration>,
+    proof_store: Option<ProofStore>,
+    max_proof_wait: Duration,
+    data_proof_retries: u32,
+    data_proof_retry_delay: Duration,
+    retry_budget: RetryBudget,
+    max_head_lag: Duration,
+    health_thresholds: HealthThresholds,
+    max_slot_offset: u64,
+    max_min_confirmations: u64,
+    commitment_lookback_blocks: u64,
+    /// Commitment of the latest range end found, which stays the same until the head moves.
+    range_commitment: Mutex<Option<(u32, RangeCommitment)>>,
+    max_uri_length: usize,
+    hash_algorithm: HashAlgorithm,
+    storage_layout: StorageLayout,
+    verify_before_return: bool,
+    allow_cache_bypass: bool,
+    negative_cache: NegativeCache,
+    range_reconciliation: Mutex<RangeReconciliation>,
+    active_requests: AtomicUsize,
+}
+
+/// Caps the concurrent requests to an upstream, the permits in use telling how many are in flight.
+struct UpstreamPermits {
+    semaphore: Semaphore,
+    limit: usize,
+}
+
+impl UpstreamPermits {
+    fn in_flight(&self) -> usize {
+        self.limit - self.semaphore.available_permits()
+    }
+}
+
+/// Counts a request as active until dropped, which also happens when the handler panics or the client disconnects.
+struct ActiveRequest<'a>(&'a AtomicUsize);
+
+impl<'a> ActiveRequest<'a> {
+    fn start(active_requests: &'a AtomicUsize) -> Self {
+        active_requests.fetch_add(1, Ordering::Relaxed);
+        ActiveRequest(active_requests)
+    }
+}
+
+impl Drop for ActiveRequest<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Short-lived cache of "not bridged yet" responses, so that bursts of polls for a fresh block don't all reach the
+/// upstreams.
+struct NegativeCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(B256, u32), (Instant, Value)>>,
+}
+
+impl NegativeCache {
+    fn get(&self, block_hash: B256, index: u32) -> Option<Value> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&(block_hash, index))
+            .filter(|(inserted, _)| inserted.elapsed() < self.ttl)
+            .map(|(_, error)| error.clone())
+    }
+
+    fn insert(&self, block_hash: B256, index: u32, error: Value) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+        entries.insert((block_hash, index), (Instant::now(), error));
+    }
+
+    fn remove(&self, block_hash: B256, index: u32) {
+        self.entries.lock().unwrap().remove(&(block_hash, index));
+    }
+}
+
+/// Coherent set of defaults for a deployment, selected with `ENV_PROFILE`.
+struct Profile {
+    avail_client_url: &'static str,
+    ethereum_client_url: &'static str,
+    succinct_base_url: &'static str,
+    beaconchain_base_url: &'static str,
+    contract_address: &'static str,
+    contract_chain_id: &'static str,
+    avail_chain_name: &'static str,
+    bridge_contract_address: &'static str,
+    beacon_genesis_time: Option<u64>,
+}
+
+impl Profile {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "testnet" => Some(Profile {
+                avail_client_url: "https://avail-turing.public.blastapi.io/api",
+                ethereum_client_url: "https://ethereum-sepolia.publicnode.com",
+                succinct_base_url: "https://beaconapi.succinct.xyz/api/integrations/vectorx",
+                beaconchain_base_url: "https://sepolia.beaconcha.in/api/v1/slot",
+                contract_address: "0xe542dB219a7e2b29C7AEaEAce242c9a2Cd528F96",
+                contract_chain_id: "11155111",
+                avail_chain_name: "turing",
+                bridge_contract_address: "0x967F7DdC4ec508462231849AE81eeaa68Ad01389",
+                beacon_genesis_time: Some(1655733600),
+            }),
+            "mainnet" => Some(Profile {
+                avail_client_url: "https://avail-mainnet.public.blastapi.io/api",
+                ethereum_client_url: "https://ethereum-rpc.publicnode.com",
+                succinct_base_url: "https://beaconapi.succinct.xyz/api/integrations/vectorx",
+                beaconchain_base_url: "https://beaconcha.in/api/v1/slot",
+                contract_address: "0x02993cdC11213985b9B13224f3aF289F03bf298d",
+                contract_chain_id: "1",
+                avail_chain_name: "mainnet",
+                bridge_contract_address: "0x054fd961708D8E2B9c10a63F6157c74458889F0a",
+                beacon_genesis_time: Some(1606824023),
+            }),
+            "local" => Some(Profile {
+                avail_client_url: "http://127.0.0.1:9944",
+                ethereum_client_url: "http://127.0.0.1:8545",
+                succinct_base_url: "https://beaconapi.succinct.xyz/api/integrations/vectorx",
+                beaconchain_base_url: "https://sepolia.beaconcha.in/api/v1/slot",
+                contract_address: "0x0000000000000000000000000000000000000000",
+                contract_chain_id: "31337",
+                avail_chain_name: "development",
+                bridge_contract_address: "0x0000000000000000000000000000000000000000",
+                beacon_genesis_time: Some(1655733600),
+            }),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Deserialize)]
 struct IndexStruct {
     index: u32,
+    #[serde(default)]
+    format: ProofFormat,
+    #[serde(default)]
+    wait: bool,
+    #[serde(default)]
+    partial: bool,
+    #[serde(default)]
+    fresh: bool,
+    timeout: Option<String>,
+    #[serde(rename = "rangeHash")]
+    range_hash: Option<B256>,
+}
+
+/// Extracts the query the same way as `Query`, but explains a bad index without the deserializer's wording.
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IndexStruct {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<IndexStruct>::from_request_parts(parts, state).await {
+            Ok(Query(index_struct)) => Ok(index_struct),
+            Err(rejection) => {
+                let valid_index = parts
+                    .uri
+                    .query()
+                    .unwrap_or_default()
+                    .split('&')
+                    .filter_map(|pair| pair.strip_prefix("index="))
+                    .any(|index| index.parse::<u32>().is_ok());
+                let error = if valid_index {
+                    rejection.body_text()
+                } else {
+                    "index must be a non-negative integer".to_owned()
+                };
+                Err((
+                    StatusCode::BAD_REQUEST,
+                    [("Cache-Control", "max-age=300, must-revalidate")],
+                    Json(json!({ "error": error })),
+                )
+                    .into_response())
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct IndicesQuery {
+    indices: String,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ProofFormat {
+    #[default]
+    Json,
+    Abi,
+    Bundle,
+}
+
+sol! {
+    /// Proof input of the bridge contract's `receiveMessage`/`verifyBlobLeaf` functions.
+    struct MerkleProofInput {
+        bytes32[] dataRootProof;
+        bytes32[] leafProof;
+        bytes32 rangeHash;
+        uint256 dataRootIndex;
+        bytes32 blobRoot;
+        bytes32 bridgeRoot;
+        bytes32 leaf;
+        uint256 leafIndex;
+    }
+
+    /// Emitted by the VectorX contract when a commitment moves its head to `blockNumber`.
+    event HeadUpdate(uint32 blockNumber, bytes32 headerHash);
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageQuery {
+    index: u32,
+    eth_block_hash: B256,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AvlProofQuery {
+    #[serde(default)]
+    slot_offset: u64,
+    slots: Option<u64>,
+    min_confirmations: Option<u64>,
+    #[serde(default)]
+    encoding: NodeEncoding,
+}
+
+/// How the proof nodes of a JSON storage proof response are written out.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum NodeEncoding {
+    /// Hex strings, as the Ethereum node returns them.
+    #[default]
+    Hex,
+    /// Arrays of byte values, the raw RLP that MPT verifiers take.
+    Bytes,
+}
+
+#[derive(Deserialize)]
+struct EthereumBlock {
+    number: U64,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -82,13 +423,13 @@ struct Roots {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct AccountStorageProofResponse {
-    account_proof: Vec<String>,
+    account_proof: Vec<Bytes>,
     storage_proof: Vec<StorageProof>,
 }
 
 #[derive(Deserialize)]
 struct StorageProof {
-    proof: Vec<String>,
+    proof: Vec<Bytes>,
 }
 
 #[derive(Deserialize)]
@@ -96,6 +437,9 @@ struct SuccinctAPIResponse {
     data: Option<SuccinctAPIData>,
     error: Option<String>,
     success: Option<bool>,
+    /// Fields outside the documented shape, kept so an unexpected response can be logged in full.
+    #[serde(flatten)]
+    unknown_fields: serde_json::Map<String, Value>,
 }
 
 #[derive(Deserialize)]
@@ -121,7 +465,68 @@ struct SlotMappingResponse {
     block_number: u32,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerboseSlotMappingResponse {
+    block_hash: B256,
+    block_number: u32,
+    block_root: B256,
+    epoch: u32,
+    slot: u32,
+    exec_state_root: B256,
+}
+
+#[derive(Deserialize)]
+struct BeaconNodeResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct BeaconNodeBlock {
+    message: BeaconNodeBlockMessage,
+}
+
+#[derive(Deserialize)]
+struct BeaconNodeBlockMessage {
+    #[serde(deserialize_with = "u32_from_str")]
+    slot: u32,
+    body: BeaconNodeBlockBody,
+}
+
+#[derive(Deserialize)]
+struct BeaconNodeBlockBody {
+    execution_payload: ExecutionPayload,
+}
+
+#[derive(Deserialize)]
+struct ExecutionPayload {
+    #[serde(deserialize_with = "u32_from_str")]
+    block_number: u32,
+    block_hash: B256,
+    state_root: B256,
+}
+
+#[derive(Deserialize)]
+struct BeaconNodeBlockRoot {
+    root: B256,
+}
+
+/// The beacon node API encodes integers as decimal strings.
+fn u32_from_str<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
 #[derive(Deserialize)]
+struct BeaconSlotQuery {
+    #[serde(default)]
+    verbose: bool,
+    /// Comma separated subset of [`BEACON_SLOT_FIELDS`] to return, taking precedence over `verbose`.
+    fields: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct SuccinctAPIData {
     range_hash: B256,
@@ -130,11 +535,17 @@ struct SuccinctAPIData {
     index: u16,
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct AggregatedResponse {
     data_root_proof: Vec<B256>,
+    /// Whether each sibling of the data root proof goes on the left, as follows from `data_root_index`.
+    #[serde(default)]
+    data_root_proof_directions: Vec<bool>,
     leaf_proof: Vec<B256>,
+    /// Whether each sibling of the leaf proof goes on the left, as follows from `leaf_index`.
+    #[serde(default)]
+    leaf_proof_directions: Vec<bool>,
     range_hash: B256,
     data_root_index: u16,
     leaf: B256,
@@ -144,14 +555,90 @@ struct AggregatedResponse {
     bridge_root: B256,
     data_root_commitment: B256,
     block_hash: B256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_number: Option<u32>,
     message: Option<AddressedMessage>,
 }
 
+impl AggregatedResponse {
+    /// Combines the Avail data proof with the Succinct data root commitment of the same block.
+    fn new(
+        block_hash: B256,
+        block_number: Option<u32>,
+        data_proof_res: KateQueryDataProofResponse,
+        succinct_data: SuccinctAPIData,
+    ) -> Self {
+        AggregatedResponse {
+            data_root_proof: succinct_data.merkle_branch,
+            data_root_proof_directions: Vec::new(),
+            leaf_proof: data_proof_res.data_proof.proof,
+            leaf_proof_directions: Vec::new(),
+            range_hash: succinct_data.range_hash,
+            data_root_index: succinct_data.index,
+            leaf: data_proof_res.data_proof.leaf,
+            leaf_index: data_proof_res.data_proof.leaf_index,
+            data_root: data_proof_res.data_proof.roots.data_root,
+            blob_root: data_proof_res.data_proof.roots.blob_root,
+            bridge_root: data_proof_res.data_proof.roots.bridge_root,
+            data_root_commitment: succinct_data.data_commitment,
+            block_hash,
+            block_number,
+            message: data_proof_res.message,
+        }
+        .with_directions()
+    }
+
+    /// Fills in the proof directions, which proofs persisted before they were added lack.
+    fn with_directions(mut self) -> Self {
+        self.data_root_proof_directions =
+            proof_directions(self.data_root_index.into(), self.data_root_proof.len());
+        self.leaf_proof_directions =
+            proof_directions(self.leaf_index.into(), self.leaf_proof.len());
+        self
+    }
+}
+
+/// Reads off the bits of a leaf's index, from the bottom of the tree up, whether its sibling at each level goes on the
+/// left when hashing the pair.
+fn proof_directions(index: u64, levels: usize) -> Vec<bool> {
+    (0..levels)
+        .map(|level| level < 64 && (index >> level) & 1 == 1)
+        .collect()
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct EthProofResponse {
-    account_proof: Vec<String>,
-    storage_proof: Vec<String>,
+    account_proof: Vec<Bytes>,
+    storage_proof: Vec<Bytes>,
+}
+
+impl From<EthMultiProofResponse> for EthProofResponse {
+    /// Keeps the proof of the first slot, the only one unless several were asked for.
+    fn from(mut proof: EthMultiProofResponse) -> Self {
+        EthProofResponse {
+            account_proof: proof.account_proof,
+            storage_proof: proof.storage_proofs.swap_remove(0).proof,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AbiProofResponse {
+    proof: Bytes,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EthMultiProofResponse {
+    account_proof: Vec<Bytes>,
+    storage_proofs: Vec<KeyedStorageProof>,
+}
+
+#[derive(Serialize)]
+struct KeyedStorageProof {
+    key: B256,
+    proof: Vec<Bytes>,
 }
 
 #[derive(Serialize)]
@@ -160,6 +647,17 @@ struct HeadResponse {
     pub slot: u64,
     pub timestamp: u64,
     pub timestamp_diff: u64,
+    pub stale: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawHeadResponse {
+    block_hash: String,
+    slot: u64,
+    slot_hex: String,
+    timestamp: u64,
+    timestamp_hex: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -169,14 +667,92 @@ struct RangeBlocks {
     end: u32,
 }
 
+impl RangeBlocks {
+    /// Commitments cover the blocks after `start` up to and including `end`.
+    fn contains(&self, block_number: u32) -> bool {
+        self.start < block_number && block_number <= self.end
+    }
+
+    /// A zero range comes from a bridge deployed without any commitment yet.
+    fn is_uninitialized(&self) -> bool {
+        self.end == 0
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct RangeBlocksAPIResponse {
     data: RangeBlocks,
+    /// Filled in by `/avl/head`, the range API doesn't return it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    commitment: Option<RangeCommitment>,
+}
+
+/// Where on Ethereum the commitment ending the range was posted.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RangeCommitment {
+    ethereum_block_number: u64,
+    transaction_hash: B256,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EthereumLog {
+    block_number: U64,
+    transaction_hash: B256,
+    data: Bytes,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BridgedResponse {
+    bridged: bool,
+    block_number: u32,
+    range: RangeBlocks,
+}
+
+#[derive(Deserialize)]
+struct AvailHeader {
+    number: String,
+    /// Avail's header extension, versioned as e.g. `{"V3": {"commitment": {"dataRoot": ...}}}`.
+    #[serde(default)]
+    extension: Value,
+}
+
+impl AvailHeader {
+    fn block_number(&self) -> Result<u32, Error> {
+        u32::from_str_radix(self.number.trim_start_matches("0x"), 16)
+            .map_err(|err| Error::Custom(format!("Invalid block number: {}", err)))
+    }
+
+    /// Reads the data root out of the commitment in the header extension, whichever its version.
+    fn data_root(&self) -> Option<B256> {
+        self.extension.as_object()?.values().find_map(|extension| {
+            extension["commitment"]["dataRoot"]
+                .as_str()
+                .and_then(|data_root| data_root.parse().ok())
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DataRootResponse {
+    block_hash: B256,
+    block_number: u32,
+    data_root: B256,
 }
 
-async fn alive() -> Result<Json<Value>, StatusCode> {
-    Ok(Json(json!({ "name": "Avail Bridge API" })))
+async fn alive(State(state): State<Arc<AppState>>) -> Result<Json<Value>, StatusCode> {
+    let mut response = json!({
+        "name": state.name,
+        "version": env!("CARGO_PKG_VERSION"),
+    });
+    if let Some(network) = &state.network {
+        response["network"] = json!(network);
+    }
+    Ok(Json(response))
 }
 
 #[inline(always)]
@@ -189,395 +765,2475 @@ async fn info(State(state): State<Arc<AppState>>) -> Result<Json<Value>, StatusC
     })))
 }
 
+/// status reports the outcome of the background checks, such as whether the range API and the VectorX contract agree,
+/// how much of the retry budget is in use, and how many requests are in flight.
 #[inline(always)]
-async fn get_eth_proof(
-    Path(block_hash): Path<B256>,
-    Query(index_struct): Query<IndexStruct>,
-    State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let cloned_state = state.clone();
-    let data_proof_response_fut = tokio::spawn(async move {
-        cloned_state
-            .avail_client
-            .request(
-                "kate_queryDataProof",
-                rpc_params![index_struct.index, &block_hash],
-            )
-            .await
-    });
-    let succinct_response_fut = tokio::spawn(async move {
-        let url = format!(
-            "{}?chainName={}&contractChainId={}&contractAddress={}&blockHash={}",
-            state.succinct_base_url,
-            state.avail_chain_name,
-            state.contract_chain_id,
-            state.contract_address,
-            block_hash
-        );
+async fn status(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let range_reconciliation = state.range_reconciliation.lock().unwrap().clone();
+    (
+        StatusCode::OK,
+        [("Cache-Control", "no-store")],
+        Json(json!({
+            "rangeReconciliation": range_reconciliation,
+            "retryBudget": state.retry_budget.status(),
+            "concurrency": {
+                "activeRequests": state.active_requests.load(Ordering::Relaxed),
+                "upstreamInFlight": {
+                    "avail": state.avail_permits.in_flight(),
+                    "ethereum": state.ethereum_permits.in_flight(),
+                    "succinct": state.succinct_permits.in_flight(),
+                    "beaconchain": state.beaconchain_permits.in_flight(),
+                    "ethGetProof": state.get_proof_permits.in_flight(),
+                },
+            },
+        })),
+    )
+}
 
-        let succinct_response = state.request_client.get(url).send().await;
-        match succinct_response {
-            Ok(resp) => resp.json::<SuccinctAPIResponse>().await,
-            Err(err) => Err(err),
-        }
+/// get_health_summary rolls the health of the dependencies, the head lag and the committed range up into a single
+/// `operational`, `degraded` or `down` status for a status page.
+#[inline(always)]
+async fn get_health_summary(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let (vector_head, range_blocks) = join!(fetch_vector_head(&state), fetch_range_blocks(&state));
+    let lag = vector_head.as_ref().ok().map(|head| {
+        Duration::from_secs((Utc::now().timestamp() as u64).saturating_sub(head.timestamp))
     });
-    let (data_proof, succinct_response) = join!(data_proof_response_fut, succinct_response_fut);
-    let data_proof_res: KateQueryDataProofResponse = match data_proof {
-        Ok(resp) => match resp {
-            Ok(data) => data,
-            Err(err) => {
-                tracing::error!("❌ Cannot get kate data proof response: {:?}", err);
-                return (
-                    StatusCode::BAD_REQUEST,
-                    [("Cache-Control", "max-age=300, must-revalidate")],
-                    Json(json!({ "error": err.to_string()})),
-                );
-            }
-        },
+    let range = match range_blocks {
+        Ok(range_blocks) if !range_blocks.data.is_uninitialized() => Some(range_blocks.data),
+        Ok(_) => None,
         Err(err) => {
-            tracing::error!("❌ {:?}", err);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                [("Cache-Control", "max-age=300, must-revalidate")],
-                Json(json!({ "error": err.to_string()})),
-            );
+            tracing::warn!("⚠️ Cannot get the range for the health summary: {:?}", err);
+            None
         }
     };
-    let succinct_data = match succinct_response {
-        Ok(data) => match data {
-            Ok(SuccinctAPIResponse {
-                data: Some(data), ..
-            }) => data,
-            Ok(SuccinctAPIResponse {
-                success: Some(false),
-                error: Some(data),
-                ..
-            }) => {
-                tracing::error!("❌ Succinct API returned unsuccessfully");
-                return (
-                    StatusCode::NOT_FOUND,
-                    [("Cache-Control", "max-age=300, must-revalidate")],
-                    Json(json!({ "error": data })),
-                );
-            }
-            Err(err) => {
-                tracing::error!("❌ {:?}", err);
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    [("Cache-Control", "max-age=300, must-revalidate")],
-                    Json(json!({ "error": err.to_string()})),
-                );
-            }
-            _ => {
-                tracing::error!("❌ Succinct API returned no data");
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    [("Cache-Control", "max-age=300, must-revalidate")],
-                    Json(json!({ "error": "Succinct API returned no data"})),
-                );
-            }
+    let range_reconciliation = state.range_reconciliation.lock().unwrap().clone();
+    let components = Components {
+        avail: if vector_head.is_ok() {
+            Health::Operational
+        } else {
+            Health::Down
+        },
+        succinct: if range.is_some() {
+            Health::Operational
+        } else {
+            Health::Down
+        },
+        range_reconciliation: if range_reconciliation.diverged
+            || range_reconciliation.error.is_some()
+        {
+            Health::Degraded
+        } else {
+            Health::Operational
+        },
+        retry_budget: if state.retry_budget.is_exhausted() {
+            Health::Degraded
+        } else {
+            Health::Operational
         },
-        Err(err) => {
-            tracing::error!("❌ {:?}", err);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                [("Cache-Control", "max-age=300, must-revalidate")],
-                Json(json!({ "error": err.to_string()})),
-            );
-        }
     };
-
+    let health = components.rollup(state.health_thresholds.lag(lag));
     (
-        StatusCode::OK,
-        [("Cache-Control", "public, max-age=31536000, immutable")],
-        Json(json!(AggregatedResponse {
-            data_root_proof: succinct_data.merkle_branch,
-            leaf_proof: data_proof_res.data_proof.proof,
-            range_hash: succinct_data.range_hash,
-            data_root_index: succinct_data.index,
-            leaf: data_proof_res.data_proof.leaf,
-            leaf_index: data_proof_res.data_proof.leaf_index,
-            data_root: data_proof_res.data_proof.roots.data_root,
-            blob_root: data_proof_res.data_proof.roots.blob_root,
-            bridge_root: data_proof_res.data_proof.roots.bridge_root,
-            data_root_commitment: succinct_data.data_commitment,
-            block_hash,
-            message: data_proof_res.message
+        // Status pages that only look at the status code still see an outage.
+        if health == Health::Down {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        },
+        [("Cache-Control", "no-store")],
+        Json(json!({
+            "status": health,
+            "checkedAt": Utc::now().timestamp(),
+            "components": components,
+            "headLagSecs": lag.map(|lag| lag.as_secs()),
+            "range": range,
         })),
     )
 }
 
 #[inline(always)]
-async fn get_avl_proof(
-    Path((block_hash, message_id)): Path<(B256, U256)>,
+async fn get_eth_proof(
+    Path(block_hash): Path<B256>,
+    index_struct: IndexStruct,
+    headers: HeaderMap,
     State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let mut hasher = Keccak256::new();
-    hasher.update(
-        [
-            message_id.to_be_bytes_vec(),
-            U256::from(1).to_be_bytes_vec(),
-        ]
-        .concat(),
-    );
-    let result = hasher.finalize();
-    let proof: Result<AccountStorageProofResponse, jsonrpsee::core::Error> = state
-        .ethereum_client
-        .request(
-            "eth_getProof",
-            rpc_params![
-                state.bridge_contract_address.as_str(),
-                [B256::from_slice(&result[..]).to_string()],
-                block_hash
-            ],
+) -> Response {
+    let cbor = accepts(&headers, "application/cbor");
+    let bypass_cache =
+        state.allow_cache_bypass && (index_struct.fresh || requests_no_cache(&headers));
+    let (proof, cache_status) =
+        match eth_proof(&state, block_hash, &index_struct, bypass_cache).await {
+            Ok(proof) => proof,
+            Err(err) => return err.into_response(),
+        };
+    let proof = match render_proof(proof, index_struct.format, state.hash_algorithm, cbor) {
+        Ok(proof) => proof,
+        Err(err) => {
+            tracing::error!("❌ Cannot encode proof: {:?}", err);
+            return EndpointError::new(StatusCode::INTERNAL_SERVER_ERROR, err).into_response();
+        }
+    };
+    let mut response = (
+        StatusCode::OK,
+        [("Cache-Control", "public, max-age=31536000, immutable")],
+        proof,
+    )
+        .into_response();
+    cache_status.insert_into(response.headers_mut());
+    response
+}
+
+/// Proof of a blob or message for Ethereum, shared by `/eth/proof`, `/message` and the gRPC interface. With `partial`,
+/// a proof missing one of its halves fails with a `207` carrying the other half.
+async fn eth_proof(
+    state: &AppState,
+    block_hash: B256,
+    index_struct: &IndexStruct,
+    bypass_cache: bool,
+) -> Result<(AggregatedResponse, CacheStatus), EndpointError> {
+    let stored_proof = state
+        .proof_store
+        .as_ref()
+        .and_then(|proof_store| proof_store.get(block_hash, index_struct.index));
+    if let Some(proof) = stored_proof.clone().filter(|_| !bypass_cache) {
+        let proof: AggregatedResponse = serde_json::from_value(proof).map_err(|err| {
+            tracing::error!("❌ Cannot decode stored proof: {:?}", err);
+            EndpointError::new(StatusCode::INTERNAL_SERVER_ERROR, err)
+        })?;
+        check_range_hash(index_struct.range_hash, block_hash, proof.range_hash)?;
+        return Ok((proof, CacheStatus::Hit));
+    }
+
+    if index_struct.wait {
+        let timeout = match index_struct.timeout.as_deref().map(parse_wait_timeout) {
+            Some(Some(timeout)) => timeout.min(state.max_proof_wait),
+            Some(None) => {
+                return Err(EndpointError::new(
+                    StatusCode::BAD_REQUEST,
+                    "timeout must be a number of seconds, like 30s",
+                ));
+            }
+            None => state.max_proof_wait,
+        };
+        let block_number = match fetch_avail_block_number(state, block_hash).await {
+            Ok(Some(block_number)) => block_number,
+            Ok(None) => {
+                return Err(EndpointError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("Block {} not found", block_hash),
+                ));
+            }
+            Err(err) => {
+                tracing::error!("❌ Cannot get Avail block header: {:?}", err);
+                return Err(EndpointError::new(StatusCode::INTERNAL_SERVER_ERROR, err));
+            }
+        };
+        if !wait_until_bridged(state, block_number, timeout).await {
+            return Err(EndpointError::new(
+                StatusCode::REQUEST_TIMEOUT,
+                format!(
+                    "Block {} was not bridged within {}s",
+                    block_hash,
+                    timeout.as_secs()
+                ),
+            )
+            .uncached());
+        }
+    } else if let Some(error) = state
+        .negative_cache
+        .get(block_hash, index_struct.index)
+        .filter(|_| !bypass_cache)
+    {
+        return Err((StatusCode::NOT_FOUND, error).into());
+    }
+
+    // Both requests run as plain futures rather than spawned tasks, so they are cancelled when a client disconnects
+    // and the handler is dropped.
+    let data_proof_response_fut = fetch_data_proof(state, block_hash, index_struct.index);
+    let succinct_response_fut = fetch_succinct_data(state, block_hash);
+    let header_fut = optional_header(state, block_hash);
+    let (data_proof, succinct_response, header) =
+        join!(data_proof_response_fut, succinct_response_fut, header_fut);
+    let block_number = header
+        .as_ref()
+        .and_then(|header| header.block_number().ok());
+    let data_proof_res: Result<KateQueryDataProofResponse, (StatusCode, Value)> = match data_proof {
+        Ok(data) => Ok(data),
+        Err(err) => {
+            tracing::error!("❌ Cannot get kate data proof response: {:?}", err);
+            Err((StatusCode::BAD_REQUEST, json!({ "error": err.to_string()})))
+        }
+    };
+    if let Err((StatusCode::NOT_FOUND, error)) = &succinct_response {
+        state
+            .negative_cache
+            .insert(block_hash, index_struct.index, error.clone());
+    }
+    let succinct_data = succinct_response.and_then(|succinct_data| {
+        check_range_hash(
+            index_struct.range_hash,
+            block_hash,
+            succinct_data.range_hash,
+        )?;
+        check_succinct_block(block_hash, header.as_ref(), &succinct_data)?;
+        Ok(succinct_data)
+    });
+    let (data_proof_res, succinct_data) = match (data_proof_res, succinct_data) {
+        (Ok(data_proof_res), Ok(succinct_data)) => (data_proof_res, succinct_data),
+        (Ok(data_proof_res), Err((_, error))) if index_struct.partial => {
+            return Err(EndpointError::with_body(
+                StatusCode::MULTI_STATUS,
+                json!({
+                    "missing": "succinct",
+                    "succinctError": redact(error["error"].as_str().unwrap_or_default()),
+                    "dataProof": data_proof_res.data_proof,
+                    "message": data_proof_res.message,
+                }),
+            )
+            .uncached());
+        }
+        (Err((_, error)), Ok(succinct_data)) if index_struct.partial => {
+            return Err(EndpointError::with_body(
+                StatusCode::MULTI_STATUS,
+                json!({
+                    "missing": "dataProof",
+                    "dataProofError": redact(error["error"].as_str().unwrap_or_default()),
+                    "succinct": succinct_data,
+                }),
+            )
+            .uncached());
+        }
+        (Err(err), _) | (_, Err(err)) => return Err(err.into()),
+    };
+    state.negative_cache.remove(block_hash, index_struct.index);
+    let proof = AggregatedResponse::new(block_hash, block_number, data_proof_res, succinct_data);
+    if state.verify_before_return {
+        if let Err(err) = proof_verification::verify(&proof, state.hash_algorithm) {
+            tracing::error!(
+                "❌ Inconsistent proof of block {} index {}: {}",
+                block_hash,
+                index_struct.index,
+                err
+            );
+            return Err(EndpointError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("The upstreams returned an inconsistent proof: {}", err),
+            ));
+        }
+    }
+    let proof_json = json!(proof);
+    if stored_proof
+        .as_ref()
+        .is_some_and(|stored| *stored != proof_json)
+    {
+        tracing::warn!(
+            "⚠️ Fresh proof of block {} index {} differs from the stored one",
+            block_hash,
+            index_struct.index
+        );
+    }
+    if let Some(proof_store) = &state.proof_store {
+        if let Err(err) = proof_store
+            .insert(block_hash, index_struct.index, proof_json)
+            .await
+        {
+            tracing::error!("❌ Cannot persist proof to the proof store: {:?}", err);
+        }
+    }
+
+    let cache_status = if bypass_cache {
+        CacheStatus::Bypass
+    } else if state.proof_store.is_some() {
+        CacheStatus::Miss
+    } else {
+        CacheStatus::Uncached
+    };
+    Ok((proof, cache_status))
+}
+
+/// get_eth_proof_multi returns the proofs of several indices of a block, fetching the Succinct data shared by all of
+/// them once and the data proofs concurrently.
+#[inline(always)]
+async fn get_eth_proof_multi(
+    Path(block_hash): Path<B256>,
+    Query(indices_query): Query<IndicesQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let indices: Vec<u32> = match indices_query
+        .indices
+        .split(',')
+        .map(|index| index.trim().parse())
+        .collect()
+    {
+        Ok(indices) => indices,
+        Err(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(
+                    json!({ "error": "indices must be a comma separated list of non-negative integers"}),
+                ),
+            );
+        }
+    };
+    if indices.len() > MAX_MULTI_INDICES {
+        return (
+            StatusCode::BAD_REQUEST,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(
+                json!({ "error": format!("At most {} indices can be requested at once", MAX_MULTI_INDICES)}),
+            ),
+        );
+    }
+
+    let (data_proofs, succinct_data, header) = join!(
+        join_all(
+            indices
+                .iter()
+                .map(|&index| fetch_data_proof(&state, block_hash, index))
+        ),
+        fetch_succinct_data(&state, block_hash),
+        optional_header(&state, block_hash)
+    );
+    let block_number = header
+        .as_ref()
+        .and_then(|header| header.block_number().ok());
+    let succinct_data = succinct_data.and_then(|succinct_data| {
+        check_succinct_block(block_hash, header.as_ref(), &succinct_data)?;
+        Ok(succinct_data)
+    });
+    let succinct_data = match succinct_data {
+        Ok(succinct_data) => succinct_data,
+        Err((status, error)) => {
+            return (
+                status,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(error),
+            );
+        }
+    };
+    let mut proofs = Vec::with_capacity(indices.len());
+    for (index, data_proof) in indices.into_iter().zip(data_proofs) {
+        match data_proof {
+            Ok(data_proof) => proofs.push(AggregatedResponse::new(
+                block_hash,
+                block_number,
+                data_proof,
+                succinct_data.clone(),
+            )),
+            Err(err) => {
+                tracing::error!("❌ Cannot get kate data proof response: {:?}", err);
+                return (
+                    StatusCode::BAD_REQUEST,
+                    [("Cache-Control", "max-age=300, must-revalidate")],
+                    Json(json!({ "error": format!("Index {}: {}", index, err)})),
+                );
+            }
+        }
+    }
+    (
+        StatusCode::OK,
+        [("Cache-Control", "public, max-age=31536000, immutable")],
+        Json(json!(proofs)),
+    )
+}
+
+/// Fetches the data proof of the blob at `index`, retrying errors that look transient while the retry budget allows.
+async fn fetch_data_proof(
+    state: &AppState,
+    block_hash: B256,
+    index: u32,
+) -> Result<KateQueryDataProofResponse, Error> {
+    let _permit = acquire_permit(&state.avail_permits, "Avail").await;
+    let avail_client = match &state.avail_data_proof_client {
+        Some(avail_data_proof_client) => {
+            tracing::debug!("Fetching the data proof from the data proof client");
+            avail_data_proof_client
+        }
+        None => &state.avail_client,
+    };
+    retry(
+        "data proof",
+        Idempotent,
+        RetryPolicy {
+            retries: state.data_proof_retries,
+            delay: state.data_proof_retry_delay,
+        },
+        &state.retry_budget,
+        |err: &Error| is_transient_data_proof_error(&err.to_string()),
+        || {
+            avail_client.request::<KateQueryDataProofResponse, _>(
+                "kate_queryDataProof",
+                rpc_params![index, &block_hash],
+            )
+        },
+    )
+    .await
+}
+
+/// Fetches the Succinct proof of the block's data root against the data root commitment, a `404` meaning the block
+/// is not bridged yet.
+async fn fetch_succinct_data(
+    state: &AppState,
+    block_hash: B256,
+) -> Result<SuccinctAPIData, (StatusCode, Value)> {
+    let url = format!(
+        "{}?chainName={}&contractChainId={}&contractAddress={}&blockHash={}",
+        state.succinct_base_url,
+        state.avail_chain_name,
+        state.contract_chain_id,
+        state.contract_address,
+        block_hash
+    );
+
+    let _permit = acquire_permit(&state.succinct_permits, "Succinct").await;
+    upstream::record(&url);
+    let succinct_response = match state.succinct_client.get(url).send().await {
+        Ok(resp) => resp.json::<SuccinctAPIResponse>().await,
+        Err(err) => Err(err),
+    };
+    match succinct_response {
+        // Data from a response that says it failed can't be trusted to be a valid proof.
+        Ok(SuccinctAPIResponse {
+            success: Some(false),
+            data: Some(_),
+            error,
+            ..
+        }) => {
+            tracing::error!(
+                "❌ Succinct API returned data in an unsuccessful response, error: {:?}",
+                error
+            );
+            Err((
+                StatusCode::BAD_GATEWAY,
+                json!({ "error": "Succinct API returned data but reported failure"}),
+            ))
+        }
+        Ok(SuccinctAPIResponse {
+            data: Some(data), ..
+        }) => Ok(data),
+        Ok(SuccinctAPIResponse {
+            success: Some(false),
+            error: Some(data),
+            ..
+        }) => {
+            tracing::error!("❌ Succinct API returned unsuccessfully");
+            Err((StatusCode::NOT_FOUND, json!({ "error": data })))
+        }
+        Ok(SuccinctAPIResponse {
+            success: Some(true),
+            data: None,
+            error,
+            unknown_fields,
+        }) => {
+            tracing::error!(
+                "❌ Succinct API reported success without data, error: {:?}, other fields: {}",
+                error,
+                serde_json::Value::Object(unknown_fields)
+            );
+            Err((
+                StatusCode::BAD_GATEWAY,
+                json!({ "error": "Succinct API reported success but returned no proof data"}),
+            ))
+        }
+        Err(err) => {
+            tracing::error!("❌ {:?}", err);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({ "error": err.to_string()}),
+            ))
+        }
+        _ => {
+            tracing::error!("❌ Succinct API returned no data");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({ "error": "Succinct API returned no data"}),
+            ))
+        }
+    }
+}
+
+/// Rejects a proof against another Succinct range than the one pinned with `rangeHash`, so audits regenerating a
+/// historical proof notice when the block was committed in a different range.
+fn check_range_hash(
+    pinned: Option<B256>,
+    block_hash: B256,
+    range_hash: B256,
+) -> Result<(), (StatusCode, Value)> {
+    match pinned {
+        Some(pinned) if pinned != range_hash => Err((
+            StatusCode::NOT_FOUND,
+            json!({
+                "error": format!("Block {} is not in range {}, its proof is against range {}", block_hash, pinned, range_hash)
+            }),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Rejects a Succinct proof that doesn't lead from the block's data root to the data commitment, as when the Succinct
+/// API resolved the block hash to a neighbouring block. Without the block's header there is nothing to compare.
+fn check_succinct_block(
+    block_hash: B256,
+    header: Option<&AvailHeader>,
+    succinct_data: &SuccinctAPIData,
+) -> Result<(), (StatusCode, Value)> {
+    let Some(data_root) = header.and_then(AvailHeader::data_root) else {
+        return Ok(());
+    };
+    let data_commitment = proof_verification::merkle_root(
+        HashAlgorithm::Sha256,
+        data_root,
+        succinct_data.index.into(),
+        &succinct_data.merkle_branch,
+    );
+    if data_commitment == succinct_data.data_commitment {
+        return Ok(());
+    }
+    tracing::error!(
+        "❌ Succinct proof for block {} doesn't lead from its data root {} to the data commitment {}",
+        block_hash,
+        data_root,
+        succinct_data.data_commitment
+    );
+    Err((
+        StatusCode::BAD_GATEWAY,
+        json!({
+            "error": format!("Succinct API returned a proof for another block than {}, whose data root {} is not at index {} of the data commitment", block_hash, data_root, succinct_data.index)
+        }),
+    ))
+}
+
+/// Parses a long-poll timeout given in seconds, like `30s` or `30`.
+fn parse_wait_timeout(timeout: &str) -> Option<Duration> {
+    timeout
+        .strip_suffix('s')
+        .unwrap_or(timeout)
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Polls the committed range until it covers the block, returning false if the timeout passes first.
+async fn wait_until_bridged(state: &AppState, block_number: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match fetch_range_blocks(state).await {
+            Ok(range_blocks) if range_blocks.data.contains(block_number) => return true,
+            Ok(_) => {}
+            Err(err) => tracing::error!("❌ Cannot get range blocks: {:?}", err.to_string()),
+        }
+        if Instant::now() + BRIDGED_POLL_INTERVAL > deadline {
+            return false;
+        }
+        tokio::time::sleep(BRIDGED_POLL_INTERVAL).await;
+    }
+}
+
+/// Renders an aggregated proof in the requested format, `abi` being the ABI encoded `MerkleProofInput` and `bundle` an
+/// [`InclusionBundle`].
+fn render_proof(
+    proof: AggregatedResponse,
+    format: ProofFormat,
+    hash_algorithm: HashAlgorithm,
+    cbor: bool,
+) -> Result<Encoded, serde_json::Error> {
+    match format {
+        ProofFormat::Json => encode(&proof.with_directions(), cbor),
+        ProofFormat::Abi => {
+            let input = MerkleProofInput {
+                dataRootProof: proof.data_root_proof,
+                leafProof: proof.leaf_proof,
+                rangeHash: proof.range_hash,
+                dataRootIndex: U256::from(proof.data_root_index),
+                blobRoot: proof.blob_root,
+                bridgeRoot: proof.bridge_root,
+                leaf: proof.leaf,
+                leafIndex: U256::from(proof.leaf_index),
+            };
+            encode(
+                &AbiProofResponse {
+                    proof: Bytes::from(input.abi_encode()),
+                },
+                cbor,
+            )
+        }
+        ProofFormat::Bundle => encode(&InclusionBundle::new(proof, hash_algorithm), cbor),
+    }
+}
+
+/// Response body of the proof endpoints, in the encoding negotiated with the `Accept` header.
+enum Encoded {
+    Json(Value),
+    Cbor(Vec<u8>),
+}
+
+impl IntoResponse for Encoded {
+    fn into_response(self) -> Response {
+        let mut response = match self {
+            Encoded::Json(body) => Json(body).into_response(),
+            Encoded::Cbor(body) => ([(CONTENT_TYPE, "application/cbor")], body).into_response(),
+        };
+        response
+            .headers_mut()
+            .insert(VARY, HeaderValue::from_static("Accept"));
+        response
+    }
+}
+
+/// Error response of an endpoint, a JSON body with `error` that is cached briefly unless marked as uncached.
+struct EndpointError {
+    status: StatusCode,
+    body: Value,
+    cache_control: &'static str,
+    retry_after: Option<&'static str>,
+}
+
+impl EndpointError {
+    fn new(status: StatusCode, error: impl Display) -> Self {
+        Self::with_body(status, json!({ "error": error.to_string()}))
+    }
+
+    fn with_body(status: StatusCode, body: Value) -> Self {
+        EndpointError {
+            status,
+            body,
+            cache_control: "max-age=300, must-revalidate",
+            retry_after: None,
+        }
+    }
+
+    /// Marks an error that may clear up any moment, like a timeout or a shed request, so caches don't keep it.
+    fn uncached(mut self) -> Self {
+        self.cache_control = "no-store";
+        self
+    }
+
+    fn retry_after(mut self, retry_after: &'static str) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// The error message, redacted of upstream URLs.
+    fn message(&self) -> String {
+        self.body["error"]
+            .as_str()
+            .map(redact)
+            .unwrap_or_else(|| self.status.to_string())
+    }
+}
+
+impl From<(StatusCode, Value)> for EndpointError {
+    fn from((status, body): (StatusCode, Value)) -> Self {
+        EndpointError::with_body(status, body)
+    }
+}
+
+impl IntoResponse for EndpointError {
+    fn into_response(self) -> Response {
+        let mut response = (
+            self.status,
+            [("Cache-Control", self.cache_control)],
+            Json(self.body),
         )
-        .await;
+            .into_response();
+        if let Some(retry_after) = self.retry_after {
+            response
+                .headers_mut()
+                .insert("Retry-After", HeaderValue::from_static(retry_after));
+        }
+        response
+    }
+}
+
+/// Where a proof was served from, reported in the `X-Cache` header.
+enum CacheStatus {
+    Hit,
+    Miss,
+    Bypass,
+    /// No proof store is configured, so there is no cache to report on.
+    Uncached,
+}
+
+impl CacheStatus {
+    fn insert_into(self, headers: &mut HeaderMap) {
+        let (cache, layer) = match self {
+            CacheStatus::Hit => ("HIT", Some("file")),
+            CacheStatus::Miss => ("MISS", None),
+            CacheStatus::Bypass => ("BYPASS", None),
+            CacheStatus::Uncached => return,
+        };
+        headers.insert("X-Cache", HeaderValue::from_static(cache));
+        if let Some(layer) = layer {
+            headers.insert("X-Cache-Layer", HeaderValue::from_static(layer));
+        }
+    }
+}
+
+/// Returns true when the client lists `media_type` in its `Accept` header, JSON being the default otherwise.
+fn accepts(headers: &HeaderMap, media_type: &str) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|media_range| media_range.split(';').next())
+        .any(|accepted| accepted.trim().eq_ignore_ascii_case(media_type))
+}
+
+/// Returns true when the request carries `Cache-Control: no-cache`.
+fn requests_no_cache(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"))
+}
+
+/// Serializes `body` as JSON, or as CBOR where `B256` and `Bytes` fields become byte strings instead of hex strings.
+fn encode<T: Serialize>(body: &T, cbor: bool) -> Result<Encoded, serde_json::Error> {
+    if !cbor {
+        return Ok(Encoded::Json(serde_json::to_value(body)?));
+    }
+    let mut bytes = Vec::new();
+    ciborium::into_writer(body, &mut bytes).map_err(serde::ser::Error::custom)?;
+    Ok(Encoded::Cbor(bytes))
+}
+
+#[inline(always)]
+async fn get_avl_proof(
+    Path((block_hash, message_id)): Path<(B256, U256)>,
+    Query(avl_proof_query): Query<AvlProofQuery>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let mut response = match avl_proof(&state, block_hash, message_id, &avl_proof_query).await {
+        Ok(proof) => {
+            let cbor = accepts(&headers, "application/cbor");
+            let encoded = match avl_proof_query.slots {
+                Some(_) => encode(&proof, cbor),
+                None => encode(&EthProofResponse::from(proof), cbor),
+            };
+            let encoded = encoded.map(|encoded| match (encoded, avl_proof_query.encoding) {
+                (Encoded::Json(mut proof), NodeEncoding::Bytes) => {
+                    nodes_as_byte_arrays(&mut proof);
+                    Encoded::Json(proof)
+                }
+                (encoded, _) => encoded,
+            });
+            match encoded {
+                Ok(proof) => (
+                    StatusCode::OK,
+                    [("Cache-Control", "public, max-age=31536000, immutable")],
+                    proof,
+                )
+                    .into_response(),
+                Err(err) => {
+                    tracing::error!("❌ Cannot encode storage proof: {:?}", err);
+                    EndpointError::new(StatusCode::INTERNAL_SERVER_ERROR, err).into_response()
+                }
+            }
+        }
+        Err(err) => err.into_response(),
+    };
+    response
+        .headers_mut()
+        .insert(VARY, HeaderValue::from_static("Accept"));
+    response
+}
+
+/// Storage proof of a message at an Ethereum block, shared by `/avl/proof`, `/message` and the gRPC interface. It holds
+/// one proof per slot, `slots` defaulting to the one slot the message starts at.
+async fn avl_proof(
+    state: &AppState,
+    block_hash: B256,
+    message_id: U256,
+    avl_proof_query: &AvlProofQuery,
+) -> Result<EthMultiProofResponse, EndpointError> {
+    if avl_proof_query.slot_offset > state.max_slot_offset {
+        return Err(EndpointError::new(
+            StatusCode::BAD_REQUEST,
+            format!("slotOffset must not exceed {}", state.max_slot_offset),
+        ));
+    }
+    let slots = avl_proof_query.slots.unwrap_or(1);
+    if slots == 0 || avl_proof_query.slot_offset.saturating_add(slots - 1) > state.max_slot_offset {
+        return Err(EndpointError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "slots must be at least 1 and slotOffset + slots - 1 must not exceed {}",
+                state.max_slot_offset
+            ),
+        ));
+    }
+    if message_id > state.max_message_id {
+        tracing::error!(
+            "❌ Message id {} exceeds the maximum of {}",
+            message_id,
+            state.max_message_id
+        );
+        return Err(EndpointError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Message id must not exceed {}", state.max_message_id),
+        ));
+    }
+    if let Some(min_confirmations) = avl_proof_query.min_confirmations {
+        if min_confirmations > state.max_min_confirmations {
+            return Err(EndpointError::new(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "minConfirmations must not exceed {}",
+                    state.max_min_confirmations
+                ),
+            ));
+        }
+        match fetch_confirmations(state, block_hash).await {
+            Ok(Some(confirmations)) if confirmations < min_confirmations => {
+                return Err(EndpointError::with_body(
+                    StatusCode::TOO_EARLY,
+                    json!({
+                        "error": format!("Block {} has {} of the {} confirmations required", block_hash, confirmations, min_confirmations),
+                        "confirmations": confirmations,
+                    }),
+                )
+                .uncached());
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Err(EndpointError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("Unknown Ethereum block {}", block_hash),
+                ));
+            }
+            Err(err) => {
+                tracing::error!("❌ Cannot get the confirmations of the block: {:?}", err);
+                return Err(EndpointError::new(StatusCode::INTERNAL_SERVER_ERROR, err));
+            }
+        }
+    }
+    // Fields of a message struct spanning several slots follow the slot its entry starts at.
+    let first_slot = state
+        .storage_layout
+        .slot(message_id)
+        .wrapping_add(U256::from(avl_proof_query.slot_offset));
+    let storage_keys: Vec<B256> = (0..slots)
+        .map(|slot| B256::from(first_slot.wrapping_add(U256::from(slot))))
+        .collect();
+    // eth_getProof is heavy enough to overload a node on its own, so it is shed rather than queued past a short wait.
+    let Ok(Ok(_get_proof_permit)) = tokio::time::timeout(
+        state.get_proof_permit_wait,
+        state.get_proof_permits.semaphore.acquire(),
+    )
+    .await
+    else {
+        tracing::warn!("⏳ Concurrency limit for eth_getProof reached, shedding the request");
+        return Err(EndpointError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many storage proofs are being generated, retry later",
+        )
+        .uncached()
+        .retry_after(GET_PROOF_RETRY_AFTER));
+    };
+    let _permit = acquire_permit(&state.ethereum_permits, "Ethereum").await;
+    let get_proof = state
+        .ethereum_client
+        .request::<AccountStorageProofResponse, _>(
+            "eth_getProof",
+            rpc_params![
+                state.bridge_contract_address.as_str(),
+                storage_keys
+                    .iter()
+                    .map(|storage_key| storage_key.to_string())
+                    .collect::<Vec<_>>(),
+                block_hash
+            ],
+        );
+    let proof = match state.get_proof_timeout {
+        Some(get_proof_timeout) => {
+            let Ok(proof) = tokio::time::timeout(get_proof_timeout, get_proof).await else {
+                tracing::warn!(
+                    "⏳ eth_getProof at block {} timed out after {:?}",
+                    block_hash,
+                    get_proof_timeout
+                );
+                return Err(EndpointError::new(
+                    StatusCode::GATEWAY_TIMEOUT,
+                    format!(
+                        "The storage proof took longer than {}ms to generate",
+                        get_proof_timeout.as_millis()
+                    ),
+                )
+                .uncached());
+            };
+            proof
+        }
+        None => get_proof.await,
+    };
 
     match proof {
-        Ok(mut resp) => (
-            StatusCode::OK,
-            [("Cache-Control", "public, max-age=31536000, immutable")],
-            Json(json!(EthProofResponse {
-                account_proof: resp.account_proof,
-                storage_proof: resp.storage_proof.swap_remove(0).proof,
-            })),
-        ),
+        Ok(resp) => Ok(EthMultiProofResponse {
+            account_proof: resp.account_proof,
+            storage_proofs: storage_keys
+                .into_iter()
+                .zip(resp.storage_proof)
+                .map(|(key, storage_proof)| KeyedStorageProof {
+                    key,
+                    proof: storage_proof.proof,
+                })
+                .collect(),
+        }),
         Err(err) => {
             tracing::error!("❌ Cannot get account and storage proofs: {:?}", err);
             if err.to_string().ends_with("status code: 429") {
-                (
-                    StatusCode::TOO_MANY_REQUESTS,
-                    [("Cache-Control", "max-age=300, must-revalidate")],
-                    Json(json!({ "error": err.to_string()})),
-                )
+                Err(EndpointError::new(StatusCode::TOO_MANY_REQUESTS, err))
+            } else if is_pruned_state_error(&err.to_string()) {
+                Err(EndpointError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("The Ethereum node no longer has the state for block {}, an archive node is required for historical proofs", block_hash),
+                ))
             } else {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    [("Cache-Control", "max-age=300, must-revalidate")],
-                    Json(json!({ "error": err.to_string()})),
-                )
+                Err(EndpointError::new(StatusCode::INTERNAL_SERVER_ERROR, err))
             }
         }
     }
 }
 
-/// Creates a request to the beaconcha service for mapping slot to the block number.
-#[inline(always)]
-async fn get_beacon_slot(
-    Path(slot): Path<U256>,
+/// get_message returns the aggregated data proof of an Avail block together with the storage proof of a message at an
+/// Ethereum block, so a relayer gets both halves in one request.
+#[inline(always)]
+async fn get_message(
+    Path((block_hash, message_id)): Path<(B256, U256)>,
+    Query(message_query): Query<MessageQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let index_struct = IndexStruct {
+        index: message_query.index,
+        format: ProofFormat::Json,
+        wait: false,
+        partial: false,
+        fresh: false,
+        timeout: None,
+        range_hash: None,
+    };
+    let avl_proof_query = AvlProofQuery {
+        slot_offset: 0,
+        slots: None,
+        min_confirmations: None,
+        encoding: NodeEncoding::Hex,
+    };
+    let (eth_proof, avl_proof) = join!(
+        eth_proof(&state, block_hash, &index_struct, false),
+        avl_proof(
+            &state,
+            message_query.eth_block_hash,
+            message_id,
+            &avl_proof_query
+        ),
+    );
+    let eth_proof = eth_proof.map(|(proof, _)| json!(proof.with_directions()));
+    let avl_proof = avl_proof.map(|proof| json!(EthProofResponse::from(proof)));
+    match (eth_proof, avl_proof) {
+        (Ok(eth_proof), Ok(avl_proof)) => (
+            StatusCode::OK,
+            [("Cache-Control", "public, max-age=31536000, immutable")],
+            Json(json!({ "ethProof": eth_proof, "avlProof": avl_proof })),
+        ),
+        (Ok(eth_proof), Err(error)) => (
+            StatusCode::MULTI_STATUS,
+            [("Cache-Control", "no-store")],
+            Json(json!({
+                "missing": "avlProof",
+                "avlProofError": error.message(),
+                "ethProof": eth_proof,
+            })),
+        ),
+        (Err(error), Ok(avl_proof)) => (
+            StatusCode::MULTI_STATUS,
+            [("Cache-Control", "no-store")],
+            Json(json!({
+                "missing": "ethProof",
+                "ethProofError": error.message(),
+                "avlProof": avl_proof,
+            })),
+        ),
+        (Err(eth_proof_error), Err(avl_proof_error)) => (
+            eth_proof_error.status,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({
+                "error": "Cannot get either proof",
+                "ethProofError": eth_proof_error.message(),
+                "avlProofError": avl_proof_error.message(),
+            })),
+        ),
+    }
+}
+
+/// Returns true when an Avail data proof error is likely to clear up on retry, e.g. for a block produced moments ago.
+fn is_transient_data_proof_error(err: &str) -> bool {
+    const TRANSIENT_DATA_PROOF_ERRORS: [&str; 6] = [
+        "not available yet",
+        "request timeout",
+        "connection closed",
+        "status code: 502",
+        "status code: 503",
+        "status code: 504",
+    ];
+    let err = err.to_lowercase();
+    TRANSIENT_DATA_PROOF_ERRORS
+        .iter()
+        .any(|transient_error| err.contains(transient_error))
+}
+
+/// Counts the confirmations of an Ethereum block, the block itself being the first, or returns `None` when the node
+/// doesn't know the block.
+async fn fetch_confirmations(state: &AppState, block_hash: B256) -> Result<Option<u64>, Error> {
+    let _permit = acquire_permit(&state.ethereum_permits, "Ethereum").await;
+    let (head, block): (Result<U64, Error>, Result<Option<EthereumBlock>, Error>) = join!(
+        state
+            .ethereum_client
+            .request("eth_blockNumber", rpc_params![]),
+        state
+            .ethereum_client
+            .request("eth_getBlockByHash", rpc_params![block_hash, false]),
+    );
+    let head = head?.to::<u64>();
+    Ok(block?.map(|block| head.saturating_sub(block.number.to::<u64>()) + 1))
+}
+
+/// Rewrites the hex proof nodes of a storage proof response, single or multi-slot, as arrays of byte values.
+fn nodes_as_byte_arrays(proof: &mut Value) {
+    let to_byte_arrays = |nodes: Option<&mut Value>| {
+        for node in nodes.and_then(Value::as_array_mut).into_iter().flatten() {
+            if let Some(bytes) = node.as_str().and_then(|node| hex::decode(node).ok()) {
+                *node = json!(bytes);
+            }
+        }
+    };
+    to_byte_arrays(proof.get_mut("accountProof"));
+    to_byte_arrays(proof.get_mut("storageProof"));
+    if let Some(storage_proofs) = proof.get_mut("storageProofs").and_then(Value::as_array_mut) {
+        for storage_proof in storage_proofs {
+            to_byte_arrays(storage_proof.get_mut("proof"));
+        }
+    }
+}
+
+/// Returns true when an Ethereum node error means the requested block's state has been pruned.
+fn is_pruned_state_error(err: &str) -> bool {
+    const PRUNED_STATE_ERRORS: [&str; 4] = [
+        "missing trie node",
+        "header not found",
+        "historical state",
+        "state is not available",
+    ];
+    PRUNED_STATE_ERRORS
+        .iter()
+        .any(|pruned_state_error| err.contains(pruned_state_error))
+}
+
+/// Creates a request to the beaconcha service for mapping slot to the block number.
+#[inline(always)]
+async fn get_beacon_slot(
+    Path(slot): Path<U256>,
+    Query(beacon_slot_query): Query<BeaconSlotQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let fields: Option<Vec<&str>> = beacon_slot_query
+        .fields
+        .as_deref()
+        .map(|fields| fields.split(',').map(str::trim).collect());
+    if let Some(unknown_field) = fields
+        .iter()
+        .flatten()
+        .find(|field| !BEACON_SLOT_FIELDS.contains(field))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({
+                "error": format!("Unknown field {:?}, valid fields are {}", unknown_field, BEACON_SLOT_FIELDS.join(", "))
+            })),
+        )
+            .into_response();
+    }
+    if let Some(latest_slot) = latest_plausible_slot(&state, Utc::now().timestamp() as u64) {
+        if slot > U256::from(latest_slot) {
+            return (
+                StatusCode::BAD_REQUEST,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(json!({
+                    "error": format!("Slot {} is in the future, the latest slot is at most {}", slot, latest_slot)
+                })),
+            )
+                .into_response();
+        }
+    }
+    let _permit = acquire_permit(&state.beaconchain_permits, "beaconcha.in").await;
+    match fetch_slot_with_rotation(&state, slot).await {
+        Ok((provider, slot_data)) => (
+            StatusCode::OK,
+            [
+                ("Cache-Control", "public, max-age=31536000, immutable"),
+                (BEACON_PROVIDER_HEADER, provider.name()),
+            ],
+            Json(if beacon_slot_query.verbose || fields.is_some() {
+                let mut response = json!(VerboseSlotMappingResponse {
+                    block_number: slot_data.exec_block_number,
+                    block_hash: slot_data.exec_block_hash,
+                    block_root: slot_data.blockroot,
+                    epoch: slot_data.epoch,
+                    slot: slot_data.slot,
+                    exec_state_root: slot_data.exec_state_root,
+                });
+                if let (Some(fields), Value::Object(response)) = (&fields, &mut response) {
+                    response.retain(|field, _| fields.contains(&field.as_str()));
+                }
+                response
+            } else {
+                json!(SlotMappingResponse {
+                    block_number: slot_data.exec_block_number,
+                    block_hash: slot_data.exec_block_hash
+                })
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({ "error": err})),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum BeaconProvider {
+    Beaconchain,
+    BeaconNode,
+}
+
+impl BeaconProvider {
+    fn name(self) -> &'static str {
+        match self {
+            BeaconProvider::Beaconchain => "beaconcha.in",
+            BeaconProvider::BeaconNode => "beacon-node",
+        }
+    }
+}
+
+/// Tries the configured beacon providers in turn, beaconcha.in first, until one returns the slot or
+/// `BEACON_MAX_ATTEMPTS` attempts were made in total, returning the provider that answered along with the slot data.
+/// The error is the first one, beaconcha.in's being the most telling when it is the main provider.
+async fn fetch_slot_with_rotation(
+    state: &AppState,
+    slot: U256,
+) -> Result<(BeaconProvider, BeaconAPIResponseData), String> {
+    let providers: &[BeaconProvider] = if state.beacon_node_url.is_some() {
+        &[BeaconProvider::Beaconchain, BeaconProvider::BeaconNode]
+    } else {
+        &[BeaconProvider::Beaconchain]
+    };
+    let mut first_err = None;
+    for &provider in providers
+        .iter()
+        .cycle()
+        .take(state.beacon_max_attempts.get() as usize)
+    {
+        let slot_data = match provider {
+            BeaconProvider::Beaconchain => fetch_beaconchain_slot(state, slot).await,
+            BeaconProvider::BeaconNode => {
+                fetch_beacon_node_slot(state, slot).await.map_err(|err| {
+                    tracing::error!("❌ Cannot get beacon node data: {:?}", err);
+                    err.to_string()
+                })
+            }
+        };
+        match slot_data {
+            Ok(slot_data) => return Ok((provider, slot_data)),
+            Err(err) => {
+                tracing::warn!(
+                    "⚠️ Beacon provider {} failed for slot {}: {}",
+                    provider.name(),
+                    slot,
+                    err
+                );
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    Err(first_err.unwrap_or_default())
+}
+
+/// Returns the latest slot that can exist at `now` according to the genesis time, allowing an epoch of clock skew, or
+/// `None` when the genesis time is unknown.
+fn latest_plausible_slot(state: &AppState, now: u64) -> Option<u64> {
+    let genesis_time = state.beacon_genesis_time?;
+    Some(now.saturating_sub(genesis_time) / state.seconds_per_slot.get() + SLOTS_PER_EPOCH as u64)
+}
+
+/// Fetches the slot data from beaconcha.in.
+async fn fetch_beaconchain_slot(
+    state: &AppState,
+    slot: U256,
+) -> Result<BeaconAPIResponseData, String> {
+    let url = format!("{}/{}", state.beaconchain_base_url, slot);
+    upstream::record(&url);
+    let resp = state.beacon_client.get(url).send().await.map_err(|err| {
+        tracing::error!("❌ Cannot get beacon API data: {:?}", err);
+        err.to_string()
+    })?;
+    let rsp_data = resp.json::<BeaconAPIResponse>().await.map_err(|err| {
+        tracing::error!("❌ Cannot get beacon API response data: {:?}", err);
+        err.to_string()
+    })?;
+    if rsp_data.status == "OK" {
+        Ok(rsp_data.data)
+    } else {
+        tracing::error!(
+            "❌ Beacon API returned unsuccessfully: {:?}",
+            rsp_data.status
+        );
+        Err("Cannot fetch slot data".to_owned())
+    }
+}
+
+/// Builds the slot data from a beacon node's standard API, for when beaconcha.in is unavailable.
+async fn fetch_beacon_node_slot(
+    state: &AppState,
+    slot: U256,
+) -> Result<BeaconAPIResponseData, reqwest::Error> {
+    let beacon_node_url = state.beacon_node_url.as_deref().unwrap_or_default();
+    upstream::record(beacon_node_url);
+    let block_fut = async {
+        state
+            .beacon_client
+            .get(format!("{}/eth/v2/beacon/blocks/{}", beacon_node_url, slot))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<BeaconNodeResponse<BeaconNodeBlock>>()
+            .await
+    };
+    let block_root_fut = async {
+        state
+            .beacon_client
+            .get(format!(
+                "{}/eth/v1/beacon/blocks/{}/root",
+                beacon_node_url, slot
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<BeaconNodeResponse<BeaconNodeBlockRoot>>()
+            .await
+    };
+    let (block, block_root) = join!(block_fut, block_root_fut);
+    let (block, block_root) = (block?.data.message, block_root?.data.root);
+    Ok(BeaconAPIResponseData {
+        blockroot: block_root,
+        exec_block_number: block.body.execution_payload.block_number,
+        epoch: block.slot / SLOTS_PER_EPOCH,
+        slot: block.slot,
+        exec_state_root: block.body.execution_payload.state_root,
+        exec_block_hash: block.body.execution_payload.block_hash,
+    })
+}
+
+/// get_eth_head returns Ethereum head with the latest slot/block that is stored and a time.
+#[inline(always)]
+async fn get_eth_head(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match fetch_vector_head(&state).await {
+        Ok(RawHeadResponse {
+            slot, timestamp, ..
+        }) => {
+            let now = Utc::now().timestamp() as u64;
+            let timestamp_diff = now.saturating_sub(timestamp);
+            let stale = timestamp_diff > state.max_head_lag.as_secs();
+            if stale {
+                tracing::warn!(
+                    "🚨 Ethereum head at slot {} is {}s old, the bridge may be stalled",
+                    slot,
+                    timestamp_diff
+                );
+            }
+            (
+                StatusCode::OK,
+                [("Cache-Control", "public, max-age=7200, must-revalidate")],
+                Json(json!(HeadResponse {
+                    slot,
+                    timestamp,
+                    timestamp_diff,
+                    stale,
+                })),
+            )
+        }
+        Err(err) if err.to_string().ends_with("status code: 429") => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({ "error": err.to_string()})),
+        ),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({ "error": err.to_string()})),
+        ),
+    }
+}
+
+/// get_avl_head_raw returns the Vector pallet's head slot and timestamp as stored on Avail, decoded and as raw hex.
+#[inline(always)]
+async fn get_avl_head_raw(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match fetch_vector_head(&state).await {
+        Ok(raw_head) => (
+            StatusCode::OK,
+            [("Cache-Control", "max-age=60, must-revalidate")],
+            Json(json!(raw_head)),
+        ),
+        Err(err) if err.to_string().ends_with("status code: 429") => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({ "error": err.to_string()})),
+        ),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({ "error": err.to_string()})),
+        ),
+    }
+}
+
+/// Reads the Vector pallet's head slot and its timestamp from storage at the finalized Avail block.
+async fn fetch_vector_head(state: &AppState) -> Result<RawHeadResponse, Error> {
+    let pallet = "Vector";
+    let head_key = storage_key::plain(pallet, "Head");
+
+    let _permit = acquire_permit(&state.avail_permits, "Avail").await;
+    let finalized_block_hash: String = state
+        .avail_client
+        .request("chain_getFinalizedHead", rpc_params![])
+        .await
+        .inspect_err(|err| {
+            tracing::error!(
+                "❌ Cannot get the latest finalized block hash: {:?}",
+                err.to_string()
+            );
+        })?;
+    let slot_hex: String = state
+        .avail_client
+        .request(
+            "state_getStorage",
+            rpc_params![head_key, finalized_block_hash.clone()],
+        )
+        .await
+        .inspect_err(|err| {
+            tracing::error!("❌ Cannot get head storage: {:?}", err.to_string());
+        })?;
+    let slot_from_hex = sp_core::bytes::from_hex(slot_hex.as_str()).unwrap();
+    let timestamp_key = storage_key::map(
+        pallet,
+        "Timestamps",
+        StorageHasher::Identity,
+        &slot_from_hex,
+    );
+    let timestamp_hex: String = state
+        .avail_client
+        .request(
+            "state_getStorage",
+            rpc_params![timestamp_key, finalized_block_hash.clone()],
+        )
+        .await
+        .inspect_err(|err| {
+            tracing::error!("❌ Cannot get timestamp storage: {:?}", err);
+        })?;
+
+    // decode response from storage into readable values
+    let slot: u64 = Decode::decode(&mut slot_from_hex.as_slice()).unwrap();
+    let timestamp_from_hex = sp_core::bytes::from_hex(timestamp_hex.as_str()).unwrap();
+    let timestamp: u64 = Decode::decode(&mut timestamp_from_hex.as_slice()).unwrap();
+    Ok(RawHeadResponse {
+        block_hash: finalized_block_hash,
+        slot,
+        slot_hex,
+        timestamp,
+        timestamp_hex,
+    })
+}
+
+/// get_avl_head returns start and end blocks which the contract has commitments
+#[inline(always)]
+async fn get_avl_head(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match avl_head(&state).await {
+        Ok(range_blocks) => (
+            StatusCode::OK,
+            [("Cache-Control", "public, max-age=900, must-revalidate")],
+            Json(json!(range_blocks)),
+        )
+            .into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+/// Committed range of Avail blocks with the commitment of its end, shared by `/avl/head` and the gRPC interface.
+async fn avl_head(state: &AppState) -> Result<RangeBlocksAPIResponse, EndpointError> {
+    match fetch_range_blocks(state).await {
+        Ok(range_blocks) if range_blocks.data.is_uninitialized() => {
+            tracing::warn!(
+                "⚠️ Range API returned an empty range, the bridge has no commitments yet"
+            );
+            Err(EndpointError::with_body(
+                StatusCode::SERVICE_UNAVAILABLE,
+                json!({
+                    "status": "uninitialized",
+                    "error": "The bridge has no commitments yet",
+                }),
+            ))
+        }
+        Ok(mut range_blocks) => {
+            range_blocks.commitment = fetch_range_commitment(state, range_blocks.data.end).await;
+            Ok(range_blocks)
+        }
+        Err(err) => {
+            tracing::error!("❌ Cannot get avl head: {:?}", err.to_string());
+            Err(EndpointError::new(StatusCode::INTERNAL_SERVER_ERROR, err))
+        }
+    }
+}
+
+/// get_avl_bridged checks whether a block is covered by the committed range without fetching its proof.
+#[inline(always)]
+async fn get_avl_bridged(
+    Path(block_hash): Path<B256>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let (block_number, range_blocks) = join!(
+        fetch_avail_block_number(&state, block_hash),
+        fetch_range_blocks(&state)
+    );
+    let block_number = match block_number {
+        Ok(Some(block_number)) => block_number,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(json!({ "error": format!("Block {} not found", block_hash)})),
+            );
+        }
+        Err(err) => {
+            tracing::error!("❌ Cannot get Avail block header: {:?}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(json!({ "error": err.to_string()})),
+            );
+        }
+    };
+    let range_blocks = match range_blocks {
+        Ok(range_blocks) => range_blocks.data,
+        Err(err) => {
+            tracing::error!("❌ Cannot get range blocks: {:?}", err.to_string());
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(json!({ "error": err.to_string()})),
+            );
+        }
+    };
+
+    let bridged = range_blocks.contains(block_number);
+    (
+        StatusCode::OK,
+        [(
+            "Cache-Control",
+            if bridged {
+                "public, max-age=31536000, immutable"
+            } else {
+                "public, max-age=60, must-revalidate"
+            },
+        )],
+        Json(json!(BridgedResponse {
+            bridged,
+            block_number,
+            range: range_blocks,
+        })),
+    )
+}
+
+#[derive(Deserialize)]
+struct StorageKeyQuery {
+    pallet: String,
+    item: String,
+    /// SCALE encoded map key, as hex.
+    key: Option<String>,
+    hasher: Option<String>,
+}
+
+/// get_storage_key computes the storage key of a pallet item, or of a map entry when a key is given, so debugging
+/// storage doesn't need ad-hoc scripts.
+#[inline(always)]
+async fn get_storage_key(Query(query): Query<StorageKeyQuery>) -> impl IntoResponse {
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({ "error": error })),
+        )
+    };
+    if query.pallet.is_empty() || query.item.is_empty() {
+        return bad_request("pallet and item must not be empty".to_owned());
+    }
+    let storage_key = match (query.key.as_deref(), query.hasher.as_deref()) {
+        (None, None) => storage_key::plain(&query.pallet, &query.item),
+        (None, Some(_)) => return bad_request("hasher is only used with a key".to_owned()),
+        (Some(key), hasher) => {
+            let key = match hex::decode(key) {
+                Ok(key) => key,
+                Err(err) => return bad_request(format!("key must be hex encoded: {}", err)),
+            };
+            let hasher = match hasher.unwrap_or("blake2_128Concat").parse() {
+                Ok(hasher) => hasher,
+                Err(err) => return bad_request(err),
+            };
+            storage_key::map(&query.pallet, &query.item, hasher, &key)
+        }
+    };
+    (
+        StatusCode::OK,
+        [("Cache-Control", "public, max-age=31536000, immutable")],
+        Json(json!({ "storageKey": storage_key })),
+    )
+}
+
+/// get_avl_data_root returns the data root committed in an Avail block's header, to cross-check the one of a proof.
+#[inline(always)]
+async fn get_avl_data_root(
+    Path(block_hash): Path<B256>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let header = match fetch_avail_header(&state, block_hash).await {
+        Ok(Some(header)) => header,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(json!({ "error": format!("Block {} not found", block_hash)})),
+            );
+        }
+        Err(err) => {
+            tracing::error!("❌ Cannot get Avail block header: {:?}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(json!({ "error": err.to_string()})),
+            );
+        }
+    };
+    let (block_number, data_root) = match (header.block_number(), header.data_root()) {
+        (Ok(block_number), Some(data_root)) => (block_number, data_root),
+        (Err(err), _) => {
+            tracing::error!("❌ Cannot decode Avail block header: {:?}", err);
+            return (
+                StatusCode::BAD_GATEWAY,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(json!({ "error": err.to_string()})),
+            );
+        }
+        (_, None) => {
+            tracing::error!("❌ Header of block {} carries no data root", block_hash);
+            return (
+                StatusCode::BAD_GATEWAY,
+                [("Cache-Control", "max-age=300, must-revalidate")],
+                Json(
+                    json!({ "error": format!("Header of block {} carries no data root", block_hash)}),
+                ),
+            );
+        }
+    };
+    (
+        StatusCode::OK,
+        [("Cache-Control", "public, max-age=31536000, immutable")],
+        Json(json!(DataRootResponse {
+            block_hash,
+            block_number,
+            data_root,
+        })),
+    )
+}
+
+/// Fetches the range of Avail blocks which the contract has commitments for.
+async fn fetch_range_blocks(state: &AppState) -> Result<RangeBlocksAPIResponse, reqwest::Error> {
+    let url = format!(
+        "{}/{}/?contractChainId={}&contractAddress={}",
+        state.succinct_base_url, "range", state.contract_chain_id, state.contract_address
+    );
+    let _permit = acquire_permit(&state.succinct_permits, "Succinct").await;
+    upstream::record(&url);
+    state
+        .succinct_client
+        .get(url)
+        .send()
+        .await?
+        .json::<RangeBlocksAPIResponse>()
+        .await
+}
+
+/// Finds the VectorX `HeadUpdate` event that moved the head to `end` within the last `COMMITMENT_LOOKBACK_BLOCKS`
+/// Ethereum blocks, `None` if it is older than that, not indexed by the node yet, the lookup failed or it is disabled.
+/// The commitment only adds to `/avl/head`, so a failed lookup omits it rather than failing the response. Once found,
+/// it is reused until `end` moves, while a miss is looked up again on the next request.
+async fn fetch_range_commitment(state: &AppState, end: u32) -> Option<RangeCommitment> {
+    if let Some((cached_end, commitment)) = &*state.range_commitment.lock().unwrap() {
+        if *cached_end == end {
+            return Some(commitment.clone());
+        }
+    }
+    let commitment = search_range_commitment(state, end)
+        .await
+        .unwrap_or_else(|err| {
+            tracing::warn!("⚠️ Cannot get the range commitment: {:?}", err);
+            None
+        })?;
+    *state.range_commitment.lock().unwrap() = Some((end, commitment.clone()));
+    Some(commitment)
+}
+
+async fn search_range_commitment(
+    state: &AppState,
+    end: u32,
+) -> Result<Option<RangeCommitment>, Error> {
+    if state.commitment_lookback_blocks == 0 {
+        return Ok(None);
+    }
+    let _permit = acquire_permit(&state.ethereum_permits, "Ethereum").await;
+    let head: U64 = state
+        .ethereum_client
+        .request("eth_blockNumber", rpc_params![])
+        .await?;
+    let from_block = head.saturating_sub(U64::from(state.commitment_lookback_blocks - 1));
+    let logs: Vec<EthereumLog> = state
+        .ethereum_client
+        .request(
+            "eth_getLogs",
+            rpc_params![json!({
+                "address": state.contract_address,
+                "topics": [HeadUpdate::SIGNATURE_HASH],
+                "fromBlock": from_block,
+                "toBlock": head,
+            })],
+        )
+        .await?;
+    for log in logs.into_iter().rev() {
+        let (block_number, _) = HeadUpdate::abi_decode_data(&log.data, true)
+            .map_err(|err| Error::Custom(format!("Invalid HeadUpdate event: {}", err)))?;
+        if block_number == end {
+            return Ok(Some(RangeCommitment {
+                ethereum_block_number: log.block_number.to::<u64>(),
+                transaction_hash: log.transaction_hash,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Looks up the header for responses that use its block number and data root as a convenience, so a failed lookup
+/// omits them rather than failing the response.
+async fn optional_header(state: &AppState, block_hash: B256) -> Option<AvailHeader> {
+    fetch_avail_header(state, block_hash)
+        .await
+        .unwrap_or_else(|err| {
+            tracing::warn!("⚠️ Cannot get the Avail block header: {:?}", err);
+            None
+        })
+}
+
+/// Resolves an Avail block hash to its block number, `None` if the node doesn't know the block.
+async fn fetch_avail_block_number(
+    state: &AppState,
+    block_hash: B256,
+) -> Result<Option<u32>, Error> {
+    fetch_avail_header(state, block_hash)
+        .await?
+        .map(|header| header.block_number())
+        .transpose()
+}
+
+/// Fetches the header of an Avail block, `None` if the node doesn't know the block.
+async fn fetch_avail_header(
+    state: &AppState,
+    block_hash: B256,
+) -> Result<Option<AvailHeader>, Error> {
+    let _permit = acquire_permit(&state.avail_permits, "Avail").await;
+    state
+        .avail_client
+        .request("chain_getHeader", rpc_params![block_hash])
+        .await
+}
+
+/// Waits for a permit to call an upstream, logging when its concurrency limit is saturated.
+async fn acquire_permit<'a>(permits: &'a UpstreamPermits, upstream: &str) -> SemaphorePermit<'a> {
+    if permits.semaphore.available_permits() == 0 {
+        tracing::warn!(
+            "⏳ Concurrency limit for {} requests reached, waiting for a permit",
+            upstream
+        );
+    }
+    permits.semaphore.acquire().await.unwrap()
+}
+
+/// Builds the semaphore capping concurrent requests to an upstream from the given env variable.
+fn upstream_permits(name: &'static str) -> Result<UpstreamPermits, StartupError> {
+    let limit = env_or(name, DEFAULT_MAX_CONCURRENT_REQUESTS)?;
+    Ok(UpstreamPermits {
+        semaphore: Semaphore::new(limit),
+        limit,
+    })
+}
+
+/// Reads the cap on idle connections kept per host from the given env variable, `None` leaving it unbounded.
+fn pool_max_idle_per_host(name: &'static str) -> Result<Option<usize>, StartupError> {
+    match env::var(name) {
+        Ok(_) => Ok(Some(env_or(name, 0)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Builds an HTTP client for the upstreams with their own connection pool, keeping at most `pool_max_idle_per_host`
+/// idle connections to each host.
+fn http_client(pool_max_idle_per_host: Option<usize>) -> Result<Client, StartupError> {
+    let mut builder = Client::builder();
+    if let Some(pool_max_idle_per_host) = pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(pool_max_idle_per_host);
+    }
+    builder
+        .brotli(true)
+        // A redirect from an API endpoint means a misconfigured URL or gateway, and following it would only
+        // surface later as a confusing parse error of whatever page it leads to.
+        .redirect(redirect::Policy::custom(|attempt| {
+            let error = format!(
+                "Unexpected {} redirect to {}",
+                attempt.status(),
+                attempt.url()
+            );
+            attempt.error(error)
+        }))
+        .build()
+        .map_err(StartupError::HttpClient)
+}
+
+/// Builds the application state from the environment on top of the defaults of the given profile.
+async fn build_state(profile_name: &str) -> Result<AppState, StartupError> {
+    let profile = Profile::from_name(profile_name)
+        .ok_or_else(|| StartupError::UnknownProfile(profile_name.to_owned()))?;
+    // Succinct and the beacon providers get a pool each, so that their connection policies can be respected apart.
+    let succinct_pool_max_idle_per_host =
+        pool_max_idle_per_host("SUCCINCT_POOL_MAX_IDLE_PER_HOST")?;
+    let beacon_pool_max_idle_per_host = pool_max_idle_per_host("BEACON_POOL_MAX_IDLE_PER_HOST")?;
+    // reqwest reads HTTP_PROXY, HTTPS_PROXY and NO_PROXY on its own, the JSON-RPC clients borrow a client to use them
+    // too.
+    let rpc_proxy = RpcProxyLayer::from_env(&http_client(None)?);
+    if rpc_proxy.is_proxied() {
+        tracing::info!("🌐 Sending upstream requests through the configured proxy");
+    }
+    let avail_client_url =
+        env::var("AVAIL_CLIENT_URL").unwrap_or(profile.avail_client_url.to_owned());
+    let avail_data_proof_client_url = env::var("AVAIL_DATAPROOF_CLIENT_URL").ok();
+    let ethereum_client_url =
+        env::var("ETHEREUM_CLIENT_URL").unwrap_or(profile.ethereum_client_url.to_owned());
+    // Hostnames only, so that API keys carried in the URLs stay out of the logs.
+    let avail_host = upstream::host(&avail_client_url);
+    let avail_data_proof_host = avail_data_proof_client_url
+        .as_deref()
+        .and_then(upstream::host);
+    let ethereum_host = upstream::host(&ethereum_client_url);
+    let max_head_lag = Duration::from_secs(env_or("MAX_HEAD_LAG_SECS", 6 * 60 * 60)?);
+    let commitment_lookback_blocks =
+        env_or("COMMITMENT_LOOKBACK_BLOCKS", MAX_COMMITMENT_LOOKBACK_BLOCKS)?;
+    if commitment_lookback_blocks > MAX_COMMITMENT_LOOKBACK_BLOCKS {
+        return Err(StartupError::InvalidVar {
+            name: "COMMITMENT_LOOKBACK_BLOCKS",
+            value: commitment_lookback_blocks.to_string(),
+            reason: format!(
+                "must not exceed {}, the widest eth_getLogs range many providers accept",
+                MAX_COMMITMENT_LOOKBACK_BLOCKS
+            ),
+        });
+    }
+    let state = AppState {
+        name: env::var("NAME").unwrap_or("Avail Bridge API".to_owned()),
+        network: env::var("NETWORK").ok(),
+        avail_client: rpc_client("AVAIL_CLIENT_URL", avail_client_url, &rpc_proxy)?,
+        avail_data_proof_client: avail_data_proof_client_url
+            .map(|url| rpc_client("AVAIL_DATAPROOF_CLIENT_URL", url, &rpc_proxy))
+            .transpose()?,
+        ethereum_client: rpc_client("ETHEREUM_CLIENT_URL", ethereum_client_url, &rpc_proxy)?,
+        succinct_client: http_client(succinct_pool_max_idle_per_host)?,
+        beacon_client: http_client(beacon_pool_max_idle_per_host)?,
+        succinct_base_url: env::var("SUCCINCT_URL").unwrap_or(profile.succinct_base_url.to_owned()),
+        beaconchain_base_url: env::var("BEACONCHAIN_URL")
+            .unwrap_or(profile.beaconchain_base_url.to_owned()),
+        beacon_node_url: env::var("BEACON_NODE_URL").ok(),
+        beacon_genesis_time: match env::var("BEACON_GENESIS_TIME") {
+            Ok(_) => Some(env_or("BEACON_GENESIS_TIME", 0)?),
+            Err(_) => profile.beacon_genesis_time,
+        },
+        seconds_per_slot: env_or("SECONDS_PER_SLOT", NonZeroU64::new(12).unwrap())?,
+        beacon_max_attempts: env_or("BEACON_MAX_ATTEMPTS", NonZeroU32::new(2).unwrap())?,
+        contract_address: env::var("VECTORX_CONTRACT_ADDRESS")
+            .unwrap_or(profile.contract_address.to_owned()),
+        contract_chain_id: env::var("CONTRACT_CHAIN_ID")
+            .unwrap_or(profile.contract_chain_id.to_owned()),
+        avail_chain_name: env::var("AVAIL_CHAIN_NAME")
+            .unwrap_or(profile.avail_chain_name.to_owned()),
+        bridge_contract_address: env::var("BRIDGE_CONTRACT_ADDRESS")
+            .unwrap_or(profile.bridge_contract_address.to_owned()),
+        max_message_id: env_or("MAX_MESSAGE_ID", U256::from(u64::MAX))?,
+        avail_permits: upstream_permits("MAX_CONCURRENT_AVAIL_REQUESTS")?,
+        ethereum_permits: upstream_permits("MAX_CONCURRENT_ETHEREUM_REQUESTS")?,
+        succinct_permits: upstream_permits("MAX_CONCURRENT_SUCCINCT_REQUESTS")?,
+        beaconchain_permits: upstream_permits("MAX_CONCURRENT_BEACONCHAIN_REQUESTS")?,
+        get_proof_permits: upstream_permits("MAX_CONCURRENT_ETH_GETPROOF")?,
+        get_proof_permit_wait: Duration::from_millis(env_or("ETH_GETPROOF_PERMIT_WAIT_MS", 1000)?),
+        get_proof_timeout: match env_or("ETH_GETPROOF_TIMEOUT_MS", 0)? {
+            0 => None,
+            timeout => Some(Duration::from_millis(timeout)),
+        },
+        proof_store: match env::var("PROOF_STORE_PATH") {
+            Ok(path) => Some(
+                ProofStore::open(path.clone().into())
+                    .await
+                    .map_err(|reason| StartupError::ProofStore { path, reason })?,
+            ),
+            Err(_) => None,
+        },
+        data_proof_retries: env_or("DATA_PROOF_RETRIES", 2)?,
+        data_proof_retry_delay: Duration::from_millis(env_or("DATA_PROOF_RETRY_DELAY_MS", 500)?),
+        retry_budget: RetryBudget::new(
+            env_or("RETRY_BUDGET_PERCENT", 10)?,
+            env_or("RETRY_BUDGET_BURST", 10)?,
+        ),
+        max_proof_wait: Duration::from_secs(env_or("MAX_PROOF_WAIT_SECS", 60)?),
+        max_head_lag,
+        health_thresholds: HealthThresholds {
+            degraded_lag: Duration::from_secs(env_or(
+                "HEALTH_DEGRADED_LAG_SECS",
+                max_head_lag.as_secs(),
+            )?),
+            down_lag: Duration::from_secs(env_or("HEALTH_DOWN_LAG_SECS", 24 * 60 * 60)?),
+        },
+        max_slot_offset: env_or("MAX_SLOT_OFFSET", 16)?,
+        max_min_confirmations: env_or("MAX_MIN_CONFIRMATIONS", 256)?,
+        commitment_lookback_blocks,
+        max_uri_length: env_or("MAX_URI_LENGTH", 1024)?,
+        hash_algorithm: env_or("HASH_ALGORITHM", HashAlgorithm::default())?,
+        storage_layout: env_or("STORAGE_LAYOUT", StorageLayout::default())?,
+        verify_before_return: env_or("VERIFY_BEFORE_RETURN", false)?,
+        allow_cache_bypass: env_or("ALLOW_CACHE_BYPASS", false)?,
+        negative_cache: NegativeCache {
+            ttl: Duration::from_secs(env_or("NEGATIVE_CACHE_TTL_SECS", 5)?),
+            entries: Mutex::new(HashMap::new()),
+        },
+        range_reconciliation: Mutex::new(RangeReconciliation::default()),
+        range_commitment: Mutex::new(None),
+        active_requests: AtomicUsize::new(0),
+    };
+    tracing::info!(
+        profile = profile_name,
+        avail_chain_name = state.avail_chain_name,
+        contract_chain_id = state.contract_chain_id,
+        contract_address = state.contract_address,
+        bridge_contract_address = state.bridge_contract_address,
+        avail_host,
+        avail_data_proof_host,
+        ethereum_host,
+        succinct_host = upstream::host(&state.succinct_base_url),
+        beaconchain_host = upstream::host(&state.beaconchain_base_url),
+        beacon_node_host = state.beacon_node_url.as_deref().and_then(upstream::host),
+        succinct_pool_max_idle_per_host,
+        beacon_pool_max_idle_per_host,
+        max_concurrent_avail_requests = state.avail_permits.limit,
+        max_concurrent_ethereum_requests = state.ethereum_permits.limit,
+        max_concurrent_succinct_requests = state.succinct_permits.limit,
+        max_concurrent_beaconchain_requests = state.beaconchain_permits.limit,
+        storage_layout = %state.storage_layout,
+        "⚙️ Effective configuration"
+    );
+    Ok(state)
+}
+
+/// Lists the configured contracts that aren't known deployments on the configured chain. The built-in registry holds
+/// the contracts of the testnet and mainnet profiles and is extended by the `chainId:address` pairs in
+/// `KNOWN_CONTRACTS`.
+fn unknown_contracts(state: &AppState) -> Result<Vec<(&'static str, &str)>, StartupError> {
+    let mut known_contracts: Vec<(String, String)> = ["testnet", "mainnet"]
+        .into_iter()
+        .filter_map(Profile::from_name)
+        .flat_map(|profile| {
+            [profile.contract_address, profile.bridge_contract_address]
+                .map(|address| (profile.contract_chain_id.to_owned(), address.to_owned()))
+        })
+        .collect();
+    let extra_contracts = env::var("KNOWN_CONTRACTS").unwrap_or_default();
+    for entry in extra_contracts
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let Some((chain_id, address)) = entry.split_once(':') else {
+            return Err(StartupError::InvalidVar {
+                name: "KNOWN_CONTRACTS",
+                value: extra_contracts.clone(),
+                reason: format!("{:?} is not a chainId:address pair", entry),
+            });
+        };
+        known_contracts.push((chain_id.trim().to_owned(), address.trim().to_owned()));
+    }
+    Ok([
+        ("VECTORX_CONTRACT_ADDRESS", state.contract_address.as_str()),
+        (
+            "BRIDGE_CONTRACT_ADDRESS",
+            state.bridge_contract_address.as_str(),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, address)| {
+        !known_contracts.iter().any(|(chain_id, known_address)| {
+            *chain_id == state.contract_chain_id && known_address.eq_ignore_ascii_case(address)
+        })
+    })
+    .collect())
+}
+
+/// Checks that the configured chains match the ones the Ethereum and Avail clients are connected to.
+async fn verify_chains(state: &AppState) -> Result<(), String> {
+    let ethereum_chain_id: String = state
+        .ethereum_client
+        .request("eth_chainId", rpc_params![])
+        .await
+        .map_err(|err| format!("Cannot get the Ethereum chain id: {}", err))?;
+    let ethereum_chain_id = u64::from_str_radix(ethereum_chain_id.trim_start_matches("0x"), 16)
+        .map_err(|err| format!("Invalid Ethereum chain id {}: {}", ethereum_chain_id, err))?;
+    if ethereum_chain_id.to_string() != state.contract_chain_id {
+        return Err(format!(
+            "CONTRACT_CHAIN_ID is {} but the Ethereum client is connected to chain {}",
+            state.contract_chain_id, ethereum_chain_id
+        ));
+    }
+
+    let avail_chain: String = state
+        .avail_client
+        .request("system_chain", rpc_params![])
+        .await
+        .map_err(|err| format!("Cannot get the Avail chain: {}", err))?;
+    if !avail_chain
+        .to_lowercase()
+        .contains(&state.avail_chain_name.to_lowercase())
+    {
+        return Err(format!(
+            "AVAIL_CHAIN_NAME is {} but the Avail client is connected to {}",
+            state.avail_chain_name, avail_chain
+        ));
+    }
+    Ok(())
+}
+
+/// Reads the request id assigned by `SetRequestIdLayer`.
+fn request_id<B>(request: &http::Request<B>) -> Option<String> {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|request_id| request_id.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// Timeouts of individual endpoints from `TIMEOUT_<ENDPOINT>_MS`, so fast endpoints can fail early while proof
+/// generation keeps the generous `REQUEST_TIMEOUT_SECS`. `0`, the default, leaves an endpoint without its own timeout.
+#[derive(Clone, Copy, Default)]
+struct EndpointTimeouts {
+    eth_proof: Option<Duration>,
+    avl_proof: Option<Duration>,
+    head: Option<Duration>,
+    bridged: Option<Duration>,
+    beacon_slot: Option<Duration>,
+    message: Option<Duration>,
+}
+
+impl EndpointTimeouts {
+    fn from_env() -> Result<Self, StartupError> {
+        let timeout = |name: &'static str| {
+            env_or(name, 0)
+                .map(|timeout_ms| (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms)))
+        };
+        Ok(EndpointTimeouts {
+            eth_proof: timeout("TIMEOUT_ETH_PROOF_MS")?,
+            avl_proof: timeout("TIMEOUT_AVL_PROOF_MS")?,
+            head: timeout("TIMEOUT_HEAD_MS")?,
+            bridged: timeout("TIMEOUT_BRIDGED_MS")?,
+            beacon_slot: timeout("TIMEOUT_BEACON_SLOT_MS")?,
+            message: timeout("TIMEOUT_MESSAGE_MS")?,
+        })
+    }
+}
+
+/// Planned downtime announced to clients by `MAINTENANCE_MODE=true`, with `MAINTENANCE_MESSAGE` and
+/// `MAINTENANCE_RETRY_AFTER_SECS`.
+#[derive(Clone)]
+struct Maintenance {
+    message: String,
+    retry_after: String,
+}
+
+impl Maintenance {
+    fn from_env() -> Result<Option<Self>, StartupError> {
+        if !env_or("MAINTENANCE_MODE", false)? {
+            return Ok(None);
+        }
+        Ok(Some(Maintenance {
+            message: env::var("MAINTENANCE_MESSAGE")
+                .unwrap_or("The bridge API is down for planned maintenance".to_owned()),
+            retry_after: env_or::<u64>("MAINTENANCE_RETRY_AFTER_SECS", 300)?.to_string(),
+        }))
+    }
+}
+
+/// Every route of the API with its catalog entry, so the router and `/endpoints` can't drift apart. The data endpoints
+/// get their own timeout and are turned off in maintenance, while the service endpoints keep reporting the truth.
+fn routes(
+    timeouts: &EndpointTimeouts,
+    maintenance: Option<Maintenance>,
+) -> Vec<(Endpoint, MethodRouter<Arc<AppState>>)> {
+    let timed = |method_router: MethodRouter<Arc<AppState>>, timeout: Option<Duration>| {
+        // Only around the handlers, so a wrong method still gets its 405.
+        method_router
+            .route_layer(middleware::from_fn_with_state(timeout, endpoint_timeout))
+            .route_layer(middleware::from_fn_with_state(
+                maintenance.clone(),
+                maintenance_mode,
+            ))
+    };
+    vec![
+        (
+            Endpoint {
+                path: "/",
+                query_params: &[],
+                description: "Reports that the service is live, with its name and version",
+            },
+            get(alive),
+        ),
+        (
+            Endpoint {
+                path: "/info",
+                query_params: &[],
+                description: "Lists the contracts and chains the bridge is configured for",
+            },
+            get(info),
+        ),
+        (
+            Endpoint {
+                path: "/status",
+                query_params: &[],
+                description: "Reports the outcome of the background checks",
+            },
+            get(status),
+        ),
+        (
+            Endpoint {
+                path: "/health/summary",
+                query_params: &[],
+                description:
+                    "Rolls the health of the bridge up into a single status for a status page",
+            },
+            get(get_health_summary),
+        ),
+        (
+            Endpoint {
+                path: "/eth/proof/:block_hash",
+                query_params: &[
+                    "index",
+                    "format",
+                    "wait",
+                    "timeout",
+                    "partial",
+                    "rangeHash",
+                    "fresh",
+                ],
+                description:
+                    "Returns the proof of a blob or message in an Avail block for Ethereum",
+            },
+            timed(get(get_eth_proof), timeouts.eth_proof),
+        ),
+        (
+            Endpoint {
+                path: "/eth/proof/:block_hash/multi",
+                query_params: &["indices"],
+                description: "Returns the proofs of several blobs or messages in an Avail block",
+            },
+            timed(get(get_eth_proof_multi), timeouts.eth_proof),
+        ),
+        (
+            Endpoint {
+                path: "/eth/head",
+                query_params: &[],
+                description: "Returns the latest Ethereum slot and block known to the Avail chain",
+            },
+            timed(get(get_eth_head), timeouts.head),
+        ),
+        (
+            Endpoint {
+                path: "/avl/head",
+                query_params: &[],
+                description: "Returns the range of Avail blocks committed to Ethereum",
+            },
+            timed(get(get_avl_head), timeouts.head),
+        ),
+        (
+            Endpoint {
+                path: "/avl/head/raw",
+                query_params: &[],
+                description: "Returns the Vector pallet head as stored on the Avail chain",
+            },
+            timed(get(get_avl_head_raw), timeouts.head),
+        ),
+        (
+            Endpoint {
+                path: "/avl/bridged/:block_hash",
+                query_params: &[],
+                description: "Checks whether an Avail block is covered by the committed range",
+            },
+            timed(get(get_avl_bridged), timeouts.bridged),
+        ),
+        (
+            Endpoint {
+                path: "/avl/data_root/:block_hash",
+                query_params: &[],
+                description: "Returns the data root committed in an Avail block header",
+            },
+            timed(get(get_avl_data_root), timeouts.bridged),
+        ),
+        (
+            Endpoint {
+                path: "/avl/proof/:block_hash/:message_id",
+                query_params: &["slotOffset", "slots", "minConfirmations", "encoding"],
+                description:
+                    "Returns the account and storage proofs of a message sent from Ethereum",
+            },
+            timed(get(get_avl_proof), timeouts.avl_proof),
+        ),
+        (
+            Endpoint {
+                path: "/beacon/slot/:slot_number",
+                query_params: &["verbose", "fields"],
+                description: "Maps a beacon chain slot to its execution block",
+            },
+            timed(get(get_beacon_slot), timeouts.beacon_slot),
+        ),
+        (
+            Endpoint {
+                path: "/message/:block_hash/:message_id",
+                query_params: &["index", "ethBlockHash"],
+                description: "Returns both the data proof and the storage proof of a message",
+            },
+            timed(get(get_message), timeouts.message),
+        ),
+    ]
+}
+
+/// Routes for debugging the bridge, only served when `DEBUG_ENDPOINTS` is set.
+fn debug_routes() -> Vec<(Endpoint, MethodRouter<Arc<AppState>>)> {
+    vec![(
+        Endpoint {
+            path: "/debug/storage_key",
+            query_params: &["pallet", "item", "key", "hasher"],
+            description: "Computes the Substrate storage key of a pallet item or map entry",
+        },
+        get(get_storage_key),
+    )]
+}
+
+/// Registers the routes on a router.
+fn router(
+    routes: impl IntoIterator<Item = (Endpoint, MethodRouter<Arc<AppState>>)>,
+) -> Router<Arc<AppState>> {
+    routes
+        .into_iter()
+        .fold(Router::new(), |router, (endpoint, method_router)| {
+            router.route(endpoint.path, method_router)
+        })
+}
+
+/// Answers `503` with the maintenance message instead of running the handler while in maintenance.
+async fn maintenance_mode(
+    State(maintenance): State<Option<Maintenance>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(maintenance) = maintenance else {
+        return next.run(request).await;
+    };
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [
+            ("Cache-Control", "public, max-age=60".to_owned()),
+            ("Retry-After", maintenance.retry_after),
+        ],
+        Json(json!({ "status": "maintenance", "message": maintenance.message })),
+    )
+        .into_response()
+}
+
+/// Answers `504` when a request runs longer than its endpoint's own timeout or `REQUEST_TIMEOUT_SECS`, dropping the
+/// handler and its upstream requests.
+async fn endpoint_timeout(
+    State(timeout): State<Option<Duration>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(timeout) = timeout else {
+        return next.run(request).await;
+    };
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("⏳ Request timed out after {:?}", timeout);
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                [("Cache-Control", "no-store")],
+                Json(
+                    json!({ "error": format!("Request timed out after {}ms", timeout.as_millis())}),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Rejects pathologically long URIs with `414` and long path segments or query values with `400` before they are
+/// parsed, hashed or forwarded upstream.
+async fn reject_oversized_input(
     State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let resp = state
-        .request_client
-        .get(format!("{}/{}", state.beaconchain_base_url, slot))
-        .send()
-        .await;
+    request: Request,
+    next: Next,
+) -> Response {
+    let uri = request.uri();
+    if uri.to_string().len() > state.max_uri_length {
+        return (
+            StatusCode::URI_TOO_LONG,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({ "error": format!("URI must not exceed {} characters", state.max_uri_length)})),
+        )
+            .into_response();
+    }
+    let oversized = uri
+        .path()
+        .split('/')
+        .chain(
+            uri.query()
+                .unwrap_or_default()
+                .split('&')
+                .filter_map(|pair| pair.split_once('=').map(|(_, value)| value)),
+        )
+        .any(|input| input.len() > MAX_INPUT_LENGTH);
+    if oversized {
+        return (
+            StatusCode::BAD_REQUEST,
+            [("Cache-Control", "max-age=300, must-revalidate")],
+            Json(json!({ "error": format!("Path segments and query values must not exceed {} characters", MAX_INPUT_LENGTH)})),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
 
-    match resp {
-        Ok(ok) => {
-            let response_data = ok.json::<BeaconAPIResponse>().await;
-            match response_data {
-                Ok(rsp_data) => {
-                    if rsp_data.status == "OK" {
-                        (
-                            StatusCode::OK,
-                            [("Cache-Control", "public, max-age=31536000, immutable")],
-                            Json(json!(SlotMappingResponse {
-                                block_number: rsp_data.data.exec_block_number,
-                                block_hash: rsp_data.data.exec_block_hash
-                            })),
-                        )
-                    } else {
-                        tracing::error!(
-                            "❌ Beacon API returned unsuccessfully: {:?}",
-                            rsp_data.status
-                        );
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            [("Cache-Control", "max-age=300, must-revalidate")],
-                            Json(json!({ "error": "Cannot fetch slot data"})),
-                        )
-                    }
-                }
-                Err(err) => {
-                    tracing::error!("❌ Cannot get beacon API response data: {:?}", err);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        [("Cache-Control", "max-age=300, must-revalidate")],
-                        Json(json!({ "error": err.to_string()})),
-                    )
-                }
-            }
-        }
+/// Counts the requests being handled for `/status`.
+async fn track_active_request(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let _active_request = ActiveRequest::start(&state.active_requests);
+    next.run(request).await
+}
+
+/// Redacts credentials from JSON error bodies and adds the request id, so that errors reported by clients can be found
+/// in the logs.
+async fn sanitize_error_body(request: Request, next: Next) -> Response {
+    let request_id = request_id(&request);
+    let problem_details = accepts(request.headers(), PROBLEM_JSON);
+    let response = next.run(request).await;
+    if response.status().is_success() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
         Err(err) => {
-            tracing::error!("❌ Cannot get beacon API data: {:?}", err);
-            (
+            tracing::error!("❌ Cannot read error response body: {:?}", err);
+            return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                [("Cache-Control", "max-age=300, must-revalidate")],
-                Json(json!({ "error": err.to_string()})),
+                Json(json!({ "error": err.to_string(), "requestId": request_id })),
             )
+                .into_response();
+        }
+    };
+    match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(mut error_body)) if error_body.contains_key("error") => {
+            if let Some(Value::String(error)) = error_body.get_mut("error") {
+                *error = redact(error);
+            }
+            parts.headers.remove(CONTENT_LENGTH);
+            parts
+                .headers
+                .append(VARY, HeaderValue::from_static("Accept"));
+            if problem_details {
+                parts
+                    .headers
+                    .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+                let problem = to_problem_details(parts.status, error_body, request_id);
+                return Response::from_parts(parts, Body::from(problem.to_string()));
+            }
+            if let Some(request_id) = request_id {
+                error_body.insert("requestId".to_owned(), json!(request_id));
+            }
+            Response::from_parts(parts, Body::from(Value::Object(error_body).to_string()))
         }
+        _ => Response::from_parts(parts, Body::from(bytes)),
     }
 }
 
-/// get_eth_head returns Ethereum head with the latest slot/block that is stored and a time.
-#[inline(always)]
-async fn get_eth_head(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let pallet = "Vector";
-    let head = "Head";
-    let timestamp = "Timestamps";
-
-    let head_key = format!(
-        "0x{}{}",
-        hex::encode(twox_128(pallet.as_bytes())),
-        hex::encode(twox_128(head.as_bytes()))
+/// Turns an `{"error": ...}` body into RFC 7807 problem details, with a stable `type` for every status code and any
+/// other fields of the body kept as extension members.
+fn to_problem_details(
+    status: StatusCode,
+    mut error_body: serde_json::Map<String, Value>,
+    request_id: Option<String>,
+) -> Value {
+    let title = status.canonical_reason().unwrap_or("Error");
+    let mut problem = serde_json::Map::new();
+    problem.insert(
+        "type".to_owned(),
+        json!(format!(
+            "{}{}",
+            PROBLEM_TYPE_PREFIX,
+            title.to_lowercase().replace(' ', "-")
+        )),
+    );
+    problem.insert("title".to_owned(), json!(title));
+    problem.insert("status".to_owned(), json!(status.as_u16()));
+    problem.insert(
+        "detail".to_owned(),
+        error_body.remove("error").unwrap_or_default(),
     );
+    if let Some(request_id) = request_id {
+        problem.insert("instance".to_owned(), json!(request_id));
+    }
+    error_body.extend(problem);
+    Value::Object(error_body)
+}
 
-    let finalized_block_hash_response: Result<String, Error> = state
-        .avail_client
-        .request("chain_getFinalizedHead", rpc_params![])
-        .await;
+/// Renames the fields of JSON responses to snake_case for clients asking with `?case=snake` or `X-Field-Case: snake`,
+/// so older tooling doesn't need its own copy of every response type.
+async fn field_case(request: Request, next: Next) -> Response {
+    let snake_case = request
+        .uri()
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .any(|pair| pair == "case=snake")
+        || request
+            .headers()
+            .get(FIELD_CASE_HEADER)
+            .is_some_and(|case| case.as_bytes().eq_ignore_ascii_case(b"snake"));
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .append(VARY, HeaderValue::from_static(FIELD_CASE_HEADER));
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"application/json"));
+    if !snake_case || !is_json {
+        return response;
+    }
 
-    match finalized_block_hash_response {
-        Ok(finalized_block_hash) => {
-            let head_response: Result<String, Error> = state
-                .avail_client
-                .request(
-                    "state_getStorage",
-                    rpc_params![head_key, finalized_block_hash.clone()],
-                )
-                .await;
-            match head_response {
-                Ok(slot_storage_response) => {
-                    let timestamp_key = format!(
-                        "0x{}{}{}",
-                        hex::encode(twox_128(pallet.as_bytes())),
-                        hex::encode(twox_128(timestamp.as_bytes())),
-                        &slot_storage_response[2..].to_string()
-                    );
-                    let timestamp_response: Result<String, Error> = state
-                        .avail_client
-                        .request(
-                            "state_getStorage",
-                            rpc_params![timestamp_key, finalized_block_hash.clone()],
-                        )
-                        .await;
-                    match timestamp_response {
-                        Ok(timestamp_storage_response) => {
-                            // decode response from storage into readable values
-                            let slot_from_hex =
-                                sp_core::bytes::from_hex(slot_storage_response.as_str()).unwrap();
-                            let slot_input = &mut slot_from_hex.as_slice();
-                            let slot: u64 = Decode::decode(slot_input).unwrap();
-                            let timestamp_from_hex =
-                                sp_core::bytes::from_hex(timestamp_storage_response.as_str())
-                                    .unwrap();
-                            let timestamp_input = &mut timestamp_from_hex.as_slice();
-                            let timestamp: u64 = Decode::decode(timestamp_input).unwrap();
-                            let now = Utc::now().timestamp() as u64;
-                            (
-                                StatusCode::OK,
-                                [("Cache-Control", "public, max-age=7200, must-revalidate")],
-                                Json(json!(HeadResponse {
-                                    slot,
-                                    timestamp,
-                                    timestamp_diff: (now - timestamp),
-                                })),
-                            )
-                        }
-                        Err(err) => {
-                            tracing::error!("❌ Cannot get timestamp storage: {:?}", err);
-                            if err.to_string().ends_with("status code: 429") {
-                                (
-                                    StatusCode::TOO_MANY_REQUESTS,
-                                    [("Cache-Control", "max-age=300, must-revalidate")],
-                                    Json(json!({ "error": err.to_string()})),
-                                )
-                            } else {
-                                (
-                                    StatusCode::INTERNAL_SERVER_ERROR,
-                                    [("Cache-Control", "max-age=300, must-revalidate")],
-                                    Json(json!({ "error": err.to_string()})),
-                                )
-                            }
-                        }
-                    }
-                }
-                Err(err) => {
-                    tracing::error!("❌ Cannot get head storage: {:?}", err.to_string());
-                    if err.to_string().ends_with("status code: 429") {
-                        (
-                            StatusCode::TOO_MANY_REQUESTS,
-                            [("Cache-Control", "max-age=300, must-revalidate")],
-                            Json(json!({ "error": err.to_string()})),
-                        )
-                    } else {
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            [("Cache-Control", "max-age=300, must-revalidate")],
-                            Json(json!({ "error": err.to_string()})),
-                        )
-                    }
-                }
-            }
-        }
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
         Err(err) => {
-            tracing::error!(
-                "Cannot get the latest finalized block hash: {:?}",
-                err.to_string()
-            );
-            (
+            tracing::error!("❌ Cannot read response body: {:?}", err);
+            return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                [("Cache-Control", "max-age=300, must-revalidate")],
                 Json(json!({ "error": err.to_string()})),
             )
+                .into_response();
+        }
+    };
+    match serde_json::from_slice::<Value>(&bytes) {
+        Ok(mut body) => {
+            snake_case_keys(&mut body);
+            parts.headers.remove(CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(body.to_string()))
         }
+        Err(_) => Response::from_parts(parts, Body::from(bytes)),
     }
 }
 
-/// get_avl_head returns start and end blocks which the contract has commitments
-#[inline(always)]
-async fn get_avl_head(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let url = format!(
-        "{}/{}/?contractChainId={}&contractAddress={}",
-        state.succinct_base_url, "range", state.contract_chain_id, state.contract_address
-    );
-    let response = state.request_client.get(url).send().await;
-    match response {
-        Ok(ok) => {
-            let range_response = ok.json::<RangeBlocksAPIResponse>().await;
-            match range_response {
-                Ok(range_blocks) => (
-                    StatusCode::OK,
-                    [("Cache-Control", "public, max-age=900, must-revalidate")],
-                    Json(json!(range_blocks)),
-                ),
-                Err(err) => {
-                    tracing::error!("❌ Cannot parse range blocks: {:?}", err.to_string());
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        [("Cache-Control", "max-age=300, must-revalidate")],
-                        Json(json!({ "error": err.to_string()})),
-                    )
-                }
-            }
+/// Renames the keys of every object in `value` from camelCase to snake_case.
+fn snake_case_keys(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            *object = std::mem::take(object)
+                .into_iter()
+                .map(|(key, mut value)| {
+                    snake_case_keys(&mut value);
+                    (to_snake_case(&key), value)
+                })
+                .collect();
         }
-        Err(err) => {
-            tracing::error!("❌ Cannot get avl head: {:?}", err.to_string());
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                [("Cache-Control", "max-age=300, must-revalidate")],
-                Json(json!({ "error": err.to_string()})),
-            )
+        Value::Array(values) => values.iter_mut().for_each(snake_case_keys),
+        _ => {}
+    }
+}
+
+fn to_snake_case(key: &str) -> String {
+    let mut snake_case = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            snake_case.push('_');
+            snake_case.push(c.to_ascii_lowercase());
+        } else {
+            snake_case.push(c);
         }
     }
+    snake_case
 }
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
+    let stdout_layer = (!env::var("LOG_STDOUT").is_ok_and(|log_stdout| log_stdout == "false"))
+        .then(|| {
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_writer(Redacting(io::stdout))
+        });
+    // The guard flushes buffered lines on drop, so it has to live until main returns.
+    let (file_layer, file_log_guard) = match env::var("LOG_DIR") {
+        Ok(log_dir) => {
+            let file_appender = tracing_appender::rolling::daily(
+                log_dir,
+                env::var("LOG_FILE_PREFIX").unwrap_or("bridge-api.log".to_owned()),
+            );
+            let (file_writer, guard) = tracing_appender::non_blocking(file_appender);
+            let file_layer = tracing_subscriber::fmt::layer()
+                .json()
+                .with_ansi(false)
+                .with_writer(Redacting(file_writer));
+            (Some(file_layer), Some(guard))
+        }
+        Err(_) => (None, None),
+    };
     tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer().json())
+        .with(stdout_layer)
+        .with(file_layer)
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
                 "bridge_api=debug,tower_http=debug,axum::rejection=trace".into()
@@ -585,54 +3241,750 @@ async fn main() {
         )
         .init();
 
-    let shared_state = Arc::new(AppState {
-        avail_client: HttpClientBuilder::default()
-            .build(
-                env::var("AVAIL_CLIENT_URL")
-                    .unwrap_or("https://avail-turing.public.blastapi.io/api".to_owned()),
-            )
-            .unwrap(),
-        ethereum_client: HttpClientBuilder::default()
-            .build(
-                env::var("ETHEREUM_CLIENT_URL")
-                    .unwrap_or("https://ethereum-sepolia.publicnode.com".to_owned()),
-            )
-            .unwrap(),
-        request_client: Client::builder().brotli(true).build().unwrap(),
-        succinct_base_url: env::var("SUCCINCT_URL")
-            .unwrap_or("https://beaconapi.succinct.xyz/api/integrations/vectorx".to_owned()),
-        beaconchain_base_url: env::var("BEACONCHAIN_URL")
-            .unwrap_or("https://sepolia.beaconcha.in/api/v1/slot".to_owned()),
-        contract_address: env::var("VECTORX_CONTRACT_ADDRESS")
-            .unwrap_or("0xe542dB219a7e2b29C7AEaEAce242c9a2Cd528F96".to_owned()),
-        contract_chain_id: env::var("CONTRACT_CHAIN_ID").unwrap_or("11155111".to_owned()),
-        avail_chain_name: env::var("AVAIL_CHAIN_NAME").unwrap_or("turing".to_owned()),
-        bridge_contract_address: env::var("BRIDGE_CONTRACT_ADDRESS")
-            .unwrap_or("0x967F7DdC4ec508462231849AE81eeaa68Ad01389".to_owned()),
-    });
+    if let Err(err) = run().await {
+        tracing::error!("❌ {}", err);
+        // Exiting skips destructors, so the buffered log lines are flushed first.
+        drop(file_log_guard);
+        std::process::exit(1);
+    }
+}
+
+/// Starts the API and serves it until shutdown, returning the first startup step that failed.
+async fn run() -> Result<(), StartupError> {
+    let profile_name = env::var("ENV_PROFILE").unwrap_or("testnet".to_owned());
+    let shared_state = Arc::new(build_state(&profile_name).await?);
+
+    if env_or("SKIP_CHAIN_VERIFICATION", false)? {
+        tracing::warn!("⚠️ Skipping the chain verification");
+    } else {
+        verify_chains(&shared_state)
+            .await
+            .map_err(StartupError::ChainVerification)?;
+    }
+
+    let strict_contract_check = env_or("STRICT_CONTRACT_CHECK", false)?;
+    let unknown_contracts = unknown_contracts(&shared_state)?;
+    for (name, address) in &unknown_contracts {
+        let message = format!(
+            "{} {} is not a known deployment on chain {}, proofs against it may be meaningless",
+            name, address, shared_state.contract_chain_id
+        );
+        if strict_contract_check {
+            tracing::error!("❌ {}", message);
+        } else {
+            tracing::warn!("⚠️ {}", message);
+        }
+    }
+    if strict_contract_check && !unknown_contracts.is_empty() {
+        return Err(StartupError::UnknownContracts(unknown_contracts.len()));
+    }
+
+    if env::args().any(|arg| arg == "--smoke-test") {
+        return if smoke_test::run(shared_state).await {
+            Ok(())
+        } else {
+            Err(StartupError::SmokeTest)
+        };
+    }
 
-    let app = Router::new()
-        .route("/", get(alive))
-        .route("/info", get(info))
-        .route("/eth/proof/:block_hash", get(get_eth_proof))
-        .route("/eth/head", get(get_eth_head))
-        .route("/avl/head", get(get_avl_head))
-        .route("/avl/proof/:block_hash/:message_id", get(get_avl_proof))
-        .route("/beacon/slot/:slot_number", get(get_beacon_slot))
-        .layer(TraceLayer::new_for_http())
-        .layer(CompressionLayer::new())
+    // Background tasks stop once `shutdown` is cancelled, so none is killed halfway through an iteration.
+    let shutdown = CancellationToken::new();
+    let mut background_tasks = Vec::new();
+    match env_or("RANGE_RECONCILIATION_INTERVAL_SECS", 300)? {
+        0 => tracing::warn!("⚠️ Range reconciliation is disabled"),
+        interval => {
+            background_tasks.push((
+                "Range reconciliation",
+                tokio::spawn(range_reconciliation::run(
+                    shared_state.clone(),
+                    Duration::from_secs(interval),
+                    shutdown.clone(),
+                )),
+            ));
+        }
+    }
+
+    let shutdown_grace = Duration::from_secs(env_or("SHUTDOWN_GRACE_SECS", 10)?);
+
+    // Compressing small bodies such as error responses costs more latency than the bytes it saves.
+    let compression_min_size = env_or("COMPRESSION_MIN_SIZE", 1024)?;
+    let server_config = ServerConfig {
+        header_read_timeout: Duration::from_secs(env_or("HEADER_READ_TIMEOUT_SECS", 10)?),
+        idle_timeout: Duration::from_secs(env_or("IDLE_TIMEOUT_SECS", 60)?),
+        shutdown_grace,
+    };
+    let request_timeout = Duration::from_secs(env_or("REQUEST_TIMEOUT_SECS", 120)?);
+    let max_request_body_bytes = env_or("MAX_REQUEST_BODY_BYTES", 16 * 1024)?;
+    let expose_upstream = env_or("EXPOSE_UPSTREAM_HEADER", false)?;
+    let access_log = env_or("ACCESS_LOG", false)?;
+    let maintenance = Maintenance::from_env()?;
+    if maintenance.is_some() {
+        tracing::warn!("🚧 Maintenance mode is on, the data endpoints answer 503");
+    }
+    let endpoint_timeouts = EndpointTimeouts::from_env()?;
+
+    #[cfg(feature = "grpc")]
+    if let Ok(grpc_port) = env::var("GRPC_PORT") {
+        let host = env::var("HOST").unwrap_or("0.0.0.0".to_owned());
+        let address = format!("{}:{}", host, grpc_port);
+        let listener = tokio::net::TcpListener::bind(&address)
+            .await
+            .map_err(|reason| StartupError::Listen { address, reason })?;
+        tracing::info!("🚀 Serving gRPC on {} port {}", host, grpc_port);
+        background_tasks.push((
+            "gRPC server",
+            tokio::spawn(grpc::serve(
+                shared_state.clone(),
+                maintenance.clone(),
+                endpoint_timeouts,
+                listener,
+                shutdown.clone(),
+            )),
+        ));
+    }
+    #[cfg(not(feature = "grpc"))]
+    if env::var("GRPC_PORT").is_ok() {
+        tracing::warn!("⚠️ GRPC_PORT is set but the service was built without the grpc feature");
+    }
+
+    let debug_endpoints = env_or("DEBUG_ENDPOINTS", false)?;
+    let zstd_dictionary = match env::var("ZSTD_DICTIONARY_PATH") {
+        Ok(path) => {
+            let level = env_or("ZSTD_DICTIONARY_LEVEL", 3)?;
+            let dictionary = ZstdDictionary::load(std::path::Path::new(&path), level)
+                .map_err(|reason| StartupError::ZstdDictionary { path, reason })?;
+            tracing::info!(
+                "🗜️ Compressing proofs with zstd dictionary {} for clients holding it",
+                dictionary.id()
+            );
+            Some(Arc::new(dictionary))
+        }
+        Err(_) => None,
+    };
+    let mut routes = routes(&endpoint_timeouts, maintenance);
+    if debug_endpoints {
+        tracing::warn!("🔧 Debug endpoints are enabled");
+        routes.extend(debug_routes());
+    }
+    if let Some(zstd_dictionary) = &zstd_dictionary {
+        routes.push(zstd_dictionary::route(zstd_dictionary.clone()));
+    }
+    let catalog = endpoints::catalog(
+        routes
+            .iter()
+            .map(|(endpoint, _)| endpoint)
+            .chain([&endpoints::ENDPOINTS]),
+    );
+    let app = router(routes)
+        .route(
+            endpoints::ENDPOINTS.path,
+            get(move || async move {
+                (
+                    StatusCode::OK,
+                    [("Cache-Control", "public, max-age=300, must-revalidate")],
+                    Json(catalog),
+                )
+            }),
+        )
+        .layer(middleware::from_fn_with_state(
+            shared_state.clone(),
+            track_active_request,
+        ))
+        // A `504` like the endpoints' own timeouts, as a `408` would blame the client for a slow upstream.
+        .layer(middleware::from_fn_with_state(
+            Some(request_timeout),
+            endpoint_timeout,
+        ))
+        .layer(RequestBodyLimitLayer::new(max_request_body_bytes))
+        .layer(middleware::from_fn_with_state(
+            shared_state.clone(),
+            reject_oversized_input,
+        ))
+        .layer(middleware::from_fn(sanitize_error_body))
+        .layer(middleware::from_fn(field_case))
+        .layer(middleware::from_fn_with_state(
+            expose_upstream,
+            upstream::expose,
+        ))
+        .layer(
+            TraceLayer::new_for_http().make_span_with(|request: &http::Request<Body>| {
+                tracing::info_span!(
+                    "request",
+                    method = %request.method(),
+                    uri = %request.uri(),
+                    request_id = request_id(request).unwrap_or_default(),
+                )
+            }),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        // Inside the compression, where the size of most bodies is still known.
+        .layer(middleware::from_fn_with_state(access_log, access_log::log))
+        // Sets its own Content-Encoding, which the compression then leaves alone.
+        .layer(middleware::from_fn_with_state(
+            zstd_dictionary,
+            zstd_dictionary::compress,
+        ))
+        .layer(
+            CompressionLayer::new()
+                .compress_when(DefaultPredicate::new().and(SizeAbove::new(compression_min_size))),
+        )
         .layer(
             CorsLayer::new()
-                .allow_methods(vec![Method::GET])
-                .allow_origin(Any),
+                .allow_methods(vec![Method::GET, Method::HEAD])
+                .allow_headers([
+                    http::HeaderName::from_static(FIELD_CASE_HEADER),
+                    http::HeaderName::from_static(zstd_dictionary::DICTIONARY_HEADER),
+                ])
+                .allow_origin(Any)
+                .expose_headers([
+                    http::HeaderName::from_static(REQUEST_ID_HEADER),
+                    http::HeaderName::from_static("x-cache"),
+                    http::HeaderName::from_static("x-cache-layer"),
+                    http::HeaderName::from_static(upstream::UPSTREAM_HEADER),
+                    http::HeaderName::from_static(BEACON_PROVIDER_HEADER),
+                    http::HeaderName::from_static(zstd_dictionary::DICTIONARY_HEADER),
+                    http::HeaderName::from_static(zstd_dictionary::DICTIONARY_AVAILABLE_HEADER),
+                ]),
         )
         .with_state(shared_state);
+    // Routes are matched before any layer of a router runs, so a router around the app trims trailing slashes for it.
+    let app = Router::new().fallback_service(NormalizePathLayer::trim_trailing_slash().layer(app));
+
+    #[cfg(unix)]
+    if let Ok(uds_path) = env::var("UDS_PATH") {
+        tracing::info!(
+            "🚀 Listening on {} with the {} profile",
+            uds_path,
+            profile_name
+        );
+        uds::serve(std::path::Path::new(&uds_path), app, server_config)
+            .await
+            .map_err(|reason| StartupError::Listen {
+                address: uds_path,
+                reason,
+            })?;
+        stop_background_tasks(shutdown, background_tasks, shutdown_grace).await;
+        return Ok(());
+    }
 
     let host = env::var("HOST").unwrap_or("0.0.0.0".to_owned());
     let port = env::var("PORT").unwrap_or("8080".to_owned());
-    let listener = tokio::net::TcpListener::bind(format!("{}:{}", host, port))
+    let address = format!("{}:{}", host, port);
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .map_err(|reason| StartupError::Listen { address, reason })?;
+    tracing::info!(
+        "🚀 Listening on {} port {} with the {} profile",
+        host,
+        port,
+        profile_name
+    );
+    if let Err(err) = server::serve(listener, app, server_config).await {
+        tracing::error!("❌ Cannot listen for the shutdown signal: {:?}", err);
+    }
+    stop_background_tasks(shutdown, background_tasks, shutdown_grace).await;
+    Ok(())
+}
+
+/// Cancels the background tasks and waits up to `grace` in total for them to finish their current iteration.
+async fn stop_background_tasks(
+    shutdown: CancellationToken,
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
+    grace: Duration,
+) {
+    tracing::info!(
+        "🛑 Shutting down, stopping {} background tasks",
+        tasks.len()
+    );
+    shutdown.cancel();
+    let deadline = tokio::time::Instant::now() + grace;
+    for (name, task) in tasks {
+        match tokio::time::timeout_at(deadline, task).await {
+            Ok(Ok(())) => tracing::info!("✅ {} stopped", name),
+            Ok(Err(err)) => tracing::error!("❌ {} failed: {:?}", name, err),
+            Err(_) => tracing::warn!(
+                "⚠️ {} didn't stop within {}s, abandoning it",
+                name,
+                grace.as_secs()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use std::sync::atomic::AtomicBool;
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    /// State of the `local` profile, whose upstreams aren't reached by the tests below.
+    async fn test_state() -> AppState {
+        build_state("local").await.unwrap()
+    }
+
+    /// Upstream that accepts connections and never answers, holding them open until the test ends.
+    async fn hanging_upstream() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    async fn body_json(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn active_request_is_counted_until_dropped() {
+        let active_requests = AtomicUsize::new(0);
+        let first = ActiveRequest::start(&active_requests);
+        let second = ActiveRequest::start(&active_requests);
+        assert_eq!(active_requests.load(Ordering::Relaxed), 2);
+        drop(first);
+        assert_eq!(active_requests.load(Ordering::Relaxed), 1);
+        drop(second);
+        assert_eq!(active_requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn dropping_a_request_stops_counting_it() {
+        let state = Arc::new(test_state().await);
+        let mut request = Box::pin({
+            let state = state.clone();
+            async move {
+                let _active_request = ActiveRequest::start(&state.active_requests);
+                std::future::pending::<()>().await;
+            }
+        });
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), &mut request)
+                .await
+                .is_err()
+        );
+        assert_eq!(state.active_requests.load(Ordering::Relaxed), 1);
+        drop(request);
+        assert_eq!(state.active_requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn dropping_get_eth_proof_cancels_its_upstream_requests() {
+        let upstream = hanging_upstream().await;
+        let mut state = test_state().await;
+        state.avail_client = rpc_client(
+            "AVAIL_CLIENT_URL",
+            upstream.clone(),
+            &RpcProxyLayer::from_env(&Client::new()),
+        )
+        .unwrap();
+        state.succinct_base_url = upstream;
+        let state = Arc::new(state);
+        let mut request = Box::pin(get_eth_proof(
+            Path(B256::ZERO),
+            IndexStruct {
+                index: 0,
+                format: ProofFormat::Json,
+                wait: false,
+                partial: false,
+                fresh: false,
+                timeout: None,
+                range_hash: None,
+            },
+            HeaderMap::new(),
+            State(state.clone()),
+        ));
+        // Polled until the data proof and Succinct requests wait on the upstream, then dropped as on a disconnect.
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), &mut request)
+                .await
+                .is_err()
+        );
+        assert!(state.avail_permits.in_flight() > 0);
+        assert_eq!(state.succinct_permits.in_flight(), 1);
+        drop(request);
+        assert_eq!(state.avail_permits.in_flight(), 0);
+        assert_eq!(state.succinct_permits.in_flight(), 0);
+    }
+
+    /// Sends `uri` through `reject_oversized_input` to a handler answering 200.
+    async fn input_check(max_uri_length: usize, uri: &str) -> Response {
+        let mut state = test_state().await;
+        state.max_uri_length = max_uri_length;
+        let state = Arc::new(state);
+        Router::new()
+            .fallback(|| async { StatusCode::OK })
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                reject_oversized_input,
+            ))
+            .with_state(state)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn path_segments_up_to_the_input_limit_are_accepted() {
+        let at_limit = format!("/eth/proof/{}", "a".repeat(MAX_INPUT_LENGTH));
+        assert_eq!(input_check(1024, &at_limit).await.status(), StatusCode::OK);
+
+        let over_limit = format!("/eth/proof/{}", "a".repeat(MAX_INPUT_LENGTH + 1));
+        let response = input_check(1024, &over_limit).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body_json(response).await,
+            json!({ "error": "Path segments and query values must not exceed 128 characters" })
+        );
+    }
+
+    #[tokio::test]
+    async fn query_values_up_to_the_input_limit_are_accepted() {
+        let at_limit = format!("/eth/proof/0x01?index={}", "1".repeat(MAX_INPUT_LENGTH));
+        assert_eq!(input_check(1024, &at_limit).await.status(), StatusCode::OK);
+
+        let over_limit = format!(
+            "/eth/proof/0x01?wait=true&index={}",
+            "1".repeat(MAX_INPUT_LENGTH + 1)
+        );
+        let response = input_check(1024, &over_limit).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body_json(response).await,
+            json!({ "error": "Path segments and query values must not exceed 128 characters" })
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_query_values_are_left_to_the_handler() {
+        for uri in [
+            "/eth/proof/0x01",
+            "/eth/proof/0x01?",
+            "/eth/proof/0x01?index",
+            "/eth/proof/0x01?index=",
+            "/eth/proof/0x01?index=&wait",
+        ] {
+            assert_eq!(
+                input_check(1024, uri).await.status(),
+                StatusCode::OK,
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn uris_up_to_the_configured_length_are_accepted() {
+        // 1 + 100 + 1 + 98 characters.
+        let at_limit = format!("/{}/{}", "a".repeat(100), "b".repeat(98));
+        assert_eq!(input_check(200, &at_limit).await.status(), StatusCode::OK);
+
+        let over_limit = format!("/{}/{}", "a".repeat(100), "b".repeat(99));
+        let response = input_check(200, &over_limit).await;
+        assert_eq!(response.status(), StatusCode::URI_TOO_LONG);
+        assert_eq!(
+            body_json(response).await,
+            json!({ "error": "URI must not exceed 200 characters" })
+        );
+    }
+
+    #[tokio::test]
+    async fn slot_zero_is_plausible_from_genesis() {
+        let mut state = test_state().await;
+        state.beacon_genesis_time = Some(1_606_824_023);
+        state.seconds_per_slot = NonZeroU64::new(12).unwrap();
+        let epoch = SLOTS_PER_EPOCH as u64;
+        // Before genesis only the epoch of clock skew is allowed, without underflowing.
+        assert_eq!(latest_plausible_slot(&state, 0), Some(epoch));
+        assert_eq!(latest_plausible_slot(&state, 1_606_824_022), Some(epoch));
+        assert_eq!(latest_plausible_slot(&state, 1_606_824_023), Some(epoch));
+        assert_eq!(latest_plausible_slot(&state, 1_606_824_034), Some(epoch));
+        assert_eq!(
+            latest_plausible_slot(&state, 1_606_824_035),
+            Some(epoch + 1)
+        );
+
+        state.seconds_per_slot = NonZeroU64::new(5).unwrap();
+        assert_eq!(latest_plausible_slot(&state, 1_606_824_027), Some(epoch));
+        assert_eq!(
+            latest_plausible_slot(&state, 1_606_824_028),
+            Some(epoch + 1)
+        );
+
+        state.beacon_genesis_time = None;
+        assert_eq!(latest_plausible_slot(&state, 1_606_824_035), None);
+    }
+
+    #[tokio::test]
+    async fn slots_past_the_plausible_one_are_rejected_at_genesis() {
+        let mut state = test_state().await;
+        // A chain that starts now, whose latest plausible slot is the last of the first epoch.
+        state.beacon_genesis_time = Some(Utc::now().timestamp() as u64);
+        state.seconds_per_slot = NonZeroU64::new(12).unwrap();
+        let first_future_slot = SLOTS_PER_EPOCH as u64 + 1;
+        let response = get_beacon_slot(
+            Path(U256::from(first_future_slot)),
+            Query(BeaconSlotQuery {
+                verbose: false,
+                fields: None,
+            }),
+            State(Arc::new(state)),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body_json(response).await,
+            json!({ "error": "Slot 33 is in the future, the latest slot is at most 32" })
+        );
+    }
+
+    async fn index_struct(uri: &str) -> Result<IndexStruct, Response> {
+        let (mut parts, _) = Request::get(uri).body(()).unwrap().into_parts();
+        IndexStruct::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn bad_indices_are_explained() {
+        for uri in [
+            "/eth/proof/0x01",
+            "/eth/proof/0x01?wait=true",
+            "/eth/proof/0x01?index=",
+            "/eth/proof/0x01?index=abc",
+            "/eth/proof/0x01?index=1.5",
+            "/eth/proof/0x01?index=-1",
+            "/eth/proof/0x01?index=4294967296",
+        ] {
+            let Err(response) = index_struct(uri).await else {
+                panic!("{uri} was accepted");
+            };
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+            assert_eq!(
+                response.headers()["Cache-Control"],
+                "max-age=300, must-revalidate"
+            );
+            assert_eq!(
+                body_json(response).await,
+                json!({ "error": "index must be a non-negative integer" }),
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn other_bad_params_keep_the_deserializer_error() {
+        let Err(response) = index_struct("/eth/proof/0x01?index=1&wait=maybe").await else {
+            panic!("wait=maybe was accepted");
+        };
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body_json(response).await,
+            json!({ "error": "Failed to deserialize query string: provided string was not `true` or `false`" })
+        );
+    }
+
+    #[tokio::test]
+    async fn the_largest_index_is_accepted() {
+        let Ok(index_struct) = index_struct("/eth/proof/0x01?index=4294967295&format=abi").await
+        else {
+            panic!("index=4294967295 was rejected");
+        };
+        assert_eq!(index_struct.index, u32::MAX);
+        assert!(matches!(index_struct.format, ProofFormat::Abi));
+        assert!(!index_struct.wait);
+    }
+
+    #[tokio::test]
+    async fn every_route_answers_its_method() {
+        // Nothing listens on a port just released, so the health summary finds its upstreams down right away.
+        let closed = format!(
+            "http://{}",
+            TcpListener::bind("127.0.0.1:0")
+                .await
+                .unwrap()
+                .local_addr()
+                .unwrap()
+        );
+        let mut state = test_state().await;
+        state.avail_client = rpc_client(
+            "AVAIL_CLIENT_URL",
+            closed.clone(),
+            &RpcProxyLayer::from_env(&Client::new()),
+        )
+        .unwrap();
+        state.succinct_base_url = closed;
+        // Maintenance answers the data endpoints without reaching their upstreams.
+        let maintenance = Maintenance {
+            message: "Down for the test".to_owned(),
+            retry_after: "300".to_owned(),
+        };
+        let routes = routes(&EndpointTimeouts::default(), Some(maintenance));
+        let expected = [
+            ("/", StatusCode::OK),
+            ("/info", StatusCode::OK),
+            ("/status", StatusCode::OK),
+            ("/health/summary", StatusCode::SERVICE_UNAVAILABLE),
+            ("/eth/proof/:block_hash", StatusCode::SERVICE_UNAVAILABLE),
+            (
+                "/eth/proof/:block_hash/multi",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            ("/eth/head", StatusCode::SERVICE_UNAVAILABLE),
+            ("/avl/head", StatusCode::SERVICE_UNAVAILABLE),
+            ("/avl/head/raw", StatusCode::SERVICE_UNAVAILABLE),
+            ("/avl/bridged/:block_hash", StatusCode::SERVICE_UNAVAILABLE),
+            (
+                "/avl/data_root/:block_hash",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                "/avl/proof/:block_hash/:message_id",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            ("/beacon/slot/:slot_number", StatusCode::SERVICE_UNAVAILABLE),
+            (
+                "/message/:block_hash/:message_id",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        ];
+        assert_eq!(
+            routes
+                .iter()
+                .map(|(endpoint, _)| endpoint.path)
+                .collect::<Vec<_>>(),
+            expected.map(|(path, _)| path)
+        );
+        let app = router(routes).with_state(Arc::new(state));
+
+        for (path, status) in expected {
+            let uri = path
+                .replace(":block_hash", &B256::ZERO.to_string())
+                .replace(":message_id", "1")
+                .replace(":slot_number", "1");
+            let response = app
+                .clone()
+                .oneshot(Request::get(&uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "GET {uri}");
+
+            let response = app
+                .clone()
+                .oneshot(Request::post(&uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::METHOD_NOT_ALLOWED,
+                "POST {uri}"
+            );
+            assert_eq!(response.headers()[http::header::ALLOW], "GET,HEAD");
+        }
+
+        let response = app
+            .oneshot(Request::get("/eth/unknown").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Ethereum node at block 1000 whose logs hold the `HeadUpdate` to block 100, at block 7, once `indexed` is set.
+    async fn ethereum_node(indexed: Arc<AtomicBool>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let node = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| async move {
+                let result = match request["method"].as_str() {
+                    Some("eth_blockNumber") => json!("0x3e8"),
+                    Some("eth_getLogs") if indexed.load(Ordering::Relaxed) => json!([{
+                        "blockNumber": "0x7",
+                        "transactionHash": B256::repeat_byte(1),
+                        "data": Bytes::from(HeadUpdate {
+                            blockNumber: 100,
+                            headerHash: B256::ZERO,
+                        }
+                        .encode_data()),
+                    }]),
+                    _ => json!([]),
+                };
+                Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, node).await });
+        format!("http://{}", address)
+    }
+
+    fn with_ethereum_node(mut state: AppState, url: String) -> AppState {
+        state.ethereum_client = rpc_client(
+            "ETHEREUM_CLIENT_URL",
+            url,
+            &RpcProxyLayer::from_env(&Client::new()),
+        )
+        .unwrap();
+        state.commitment_lookback_blocks = 1000;
+        state
+    }
+
+    #[tokio::test]
+    async fn range_commitments_are_looked_up_once_per_end() {
+        let state = with_ethereum_node(test_state().await, hanging_upstream().await);
+        let commitment = RangeCommitment {
+            ethereum_block_number: 7,
+            transaction_hash: B256::repeat_byte(1),
+        };
+        *state.range_commitment.lock().unwrap() = Some((100, commitment));
+
+        let cached = tokio::time::timeout(
+            Duration::from_millis(200),
+            fetch_range_commitment(&state, 100),
+        )
         .await
+        .expect("the cached commitment went to Ethereum")
         .unwrap();
-    tracing::info!("🚀 Listening on {} port {}", host, port);
-    axum::serve(listener, app).await.unwrap();
+        assert_eq!(cached.ethereum_block_number, 7);
+        assert_eq!(cached.transaction_hash, B256::repeat_byte(1));
+
+        // A new range end has to be looked up.
+        assert!(tokio::time::timeout(
+            Duration::from_millis(200),
+            fetch_range_commitment(&state, 101)
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn range_commitment_misses_are_looked_up_again() {
+        let indexed = Arc::new(AtomicBool::new(false));
+        let state = with_ethereum_node(test_state().await, ethereum_node(indexed.clone()).await);
+
+        assert!(fetch_range_commitment(&state, 100).await.is_none());
+        assert!(state.range_commitment.lock().unwrap().is_none());
+
+        // The node has since indexed the event.
+        indexed.store(true, Ordering::Relaxed);
+        let commitment = fetch_range_commitment(&state, 100).await.unwrap();
+        assert_eq!(commitment.ethereum_block_number, 7);
+        assert_eq!(commitment.transaction_hash, B256::repeat_byte(1));
+    }
+
+    #[tokio::test]
+    async fn failed_range_commitment_lookups_are_left_out() {
+        // Nothing listens on the port once the listener is dropped, so the lookup fails.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let state = with_ethereum_node(test_state().await, url);
+
+        assert!(fetch_range_commitment(&state, 100).await.is_none());
+        assert!(state.range_commitment.lock().unwrap().is_none());
+    }
 }
@@ -0,0 +1,97 @@
+//! Append-only file of aggregated proofs, so that a warmed store can serve proofs without network access.
+
+use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// One line of the store file.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredProof {
+    block_hash: B256,
+    index: u32,
+    proof: Value,
+}
+
+pub struct ProofStore {
+    path: PathBuf,
+    proofs: RwLock<HashMap<(B256, u32), Value>>,
+    file: Mutex<File>,
+}
+
+impl ProofStore {
+    /// Opens the store at `path`, creating it if needed and loading the proofs it already holds.
+    pub async fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let mut proofs = HashMap::new();
+        for (line_number, line) in tokio::fs::read_to_string(&path).await?.lines().enumerate() {
+            match serde_json::from_str::<StoredProof>(line) {
+                Ok(stored) => {
+                    proofs.insert((stored.block_hash, stored.index), stored.proof);
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "⚠️ Skipping malformed line {} of {:?}: {:?}",
+                        line_number + 1,
+                        path,
+                        err
+                    );
+                }
+            }
+        }
+        tracing::info!("📦 Loaded {} proofs from {:?}", proofs.len(), path);
+
+        Ok(ProofStore {
+            path,
+            proofs: RwLock::new(proofs),
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn get(&self, block_hash: B256, index: u32) -> Option<Value> {
+        self.proofs
+            .read()
+            .unwrap()
+            .get(&(block_hash, index))
+            .cloned()
+    }
+
+    /// Persists a proof, doing nothing if the store already holds one for the same block and index.
+    pub async fn insert(&self, block_hash: B256, index: u32, proof: Value) -> io::Result<()> {
+        if self.get(block_hash, index).is_some() {
+            return Ok(());
+        }
+        let mut line = serde_json::to_string(&StoredProof {
+            block_hash,
+            index,
+            proof: proof.clone(),
+        })?;
+        line.push('\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        self.proofs
+            .write()
+            .unwrap()
+            .insert((block_hash, index), proof);
+        tracing::debug!(
+            "📦 Stored proof for {} at index {} in {:?}",
+            block_hash,
+            index,
+            self.path
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+//! Recomputes the roots of an assembled proof the way the bridge contract's `verifyBlobLeaf` and `receiveMessage` do,
+//! to catch an Avail data proof and a Succinct commitment that don't belong together before a client gets them.
+
+use crate::hashing::HashAlgorithm;
+use crate::AggregatedResponse;
+use alloy_primitives::B256;
+
+/// Checks that the leaf proof leads to the blob root, or the bridge root for a message, that both roots make up the
+/// data root, and that the data root proof leads from it to the data root commitment. The Avail trees are hashed
+/// with `algorithm`, the Succinct commitment always with SHA-256.
+pub fn verify(proof: &AggregatedResponse, algorithm: HashAlgorithm) -> Result<(), String> {
+    let data_root = algorithm.hash(&[proof.blob_root, proof.bridge_root].concat());
+    if data_root != proof.data_root {
+        return Err(format!(
+            "Data root {} doesn't match the blob and bridge roots, which hash to {}",
+            proof.data_root, data_root
+        ));
+    }
+    let (leaf_root, root_name) = match proof.message {
+        Some(_) => (proof.bridge_root, "bridge"),
+        None => (proof.blob_root, "blob"),
+    };
+    let computed_leaf_root = merkle_root(
+        algorithm,
+        algorithm.hash(proof.leaf.as_slice()),
+        proof.leaf_index.into(),
+        &proof.leaf_proof,
+    );
+    if computed_leaf_root != leaf_root {
+        return Err(format!(
+            "Leaf proof leads to {} instead of the {} root {}",
+            computed_leaf_root, root_name, leaf_root
+        ));
+    }
+    let computed_commitment = merkle_root(
+        HashAlgorithm::Sha256,
+        proof.data_root,
+        proof.data_root_index.into(),
+        &proof.data_root_proof,
+    );
+    if computed_commitment != proof.data_root_commitment {
+        return Err(format!(
+            "Data root proof leads to {} instead of the data root commitment {}",
+            computed_commitment, proof.data_root_commitment
+        ));
+    }
+    Ok(())
+}
+
+/// Walks a Merkle proof up from `leaf`, its position at each level telling which side of the pair it hashes on.
+pub fn merkle_root(algorithm: HashAlgorithm, leaf: B256, mut index: u64, proof: &[B256]) -> B256 {
+    proof.iter().fold(leaf, |node, sibling| {
+        let pair = if index & 1 == 1 {
+            [*sibling, node]
+        } else {
+            [node, *sibling]
+        };
+        index >>= 1;
+        algorithm.hash(&pair.concat())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Single-leaf blob proof whose Avail trees are hashed with `algorithm`.
+    fn proof(algorithm: HashAlgorithm) -> AggregatedResponse {
+        let leaf = B256::repeat_byte(1);
+        let blob_root = algorithm.hash(leaf.as_slice());
+        let bridge_root = B256::repeat_byte(2);
+        let data_root = algorithm.hash(&[blob_root, bridge_root].concat());
+        AggregatedResponse {
+            data_root_proof: Vec::new(),
+            data_root_proof_directions: Vec::new(),
+            leaf_proof: Vec::new(),
+            leaf_proof_directions: Vec::new(),
+            range_hash: B256::ZERO,
+            data_root_index: 0,
+            leaf,
+            leaf_index: 0,
+            data_root,
+            blob_root,
+            bridge_root,
+            data_root_commitment: data_root,
+            block_hash: B256::ZERO,
+            block_number: None,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn avail_trees_are_checked_with_the_configured_hash() {
+        for algorithm in [
+            HashAlgorithm::Keccak256,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake2,
+        ] {
+            assert_eq!(verify(&proof(algorithm), algorithm), Ok(()));
+        }
+        assert!(verify(&proof(HashAlgorithm::Blake2), HashAlgorithm::Keccak256).is_err());
+    }
+}
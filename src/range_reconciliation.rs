@@ -0,0 +1,107 @@
+//! Background check that the range reported by the Succinct range API matches the VectorX contract on Ethereum, since
+//! proofs against a range the contract doesn't know are rejected on chain.
+
+use crate::{acquire_permit, fetch_range_blocks, AppState};
+use alloy_primitives::Bytes;
+use alloy_sol_types::{sol, SolCall};
+use chrono::Utc;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::rpc_params;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::join;
+use tokio_util::sync::CancellationToken;
+
+sol! {
+    function latestBlock() external view returns (uint32);
+}
+
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeReconciliation {
+    /// Unix time of the last check, successful or not.
+    pub checked_at: Option<i64>,
+    pub range_api_end: Option<u32>,
+    pub contract_latest_block: Option<u32>,
+    /// Set once the two disagree on consecutive checks, a single mismatch being expected while a new commitment is
+    /// picked up by the range API.
+    pub diverged: bool,
+    pub error: Option<String>,
+}
+
+/// Compares the range end of the range API with the contract's `latestBlock` every `interval`, recording the outcome
+/// in the state for `/status`, until `shutdown` is cancelled. A check in progress is finished first.
+pub async fn run(state: Arc<AppState>, interval: Duration, shutdown: CancellationToken) {
+    let mut interval = tokio::time::interval(interval);
+    let mut last_mismatch = None;
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let (range_blocks, contract_latest_block) = join!(
+            fetch_range_blocks(&state),
+            fetch_contract_latest_block(&state)
+        );
+        let mut reconciliation = state.range_reconciliation.lock().unwrap().clone();
+        reconciliation.checked_at = Some(Utc::now().timestamp());
+        match (range_blocks, contract_latest_block) {
+            (Ok(range_blocks), Ok(contract_latest_block)) => {
+                let range_api_end = range_blocks.data.end;
+                let mismatch = (range_api_end != contract_latest_block)
+                    .then_some((range_api_end, contract_latest_block));
+                reconciliation.diverged = mismatch.is_some() && mismatch == last_mismatch;
+                if reconciliation.diverged {
+                    tracing::error!(
+                        "❌ Range API ends at block {} but the VectorX contract is at block {}",
+                        range_api_end,
+                        contract_latest_block
+                    );
+                } else if mismatch.is_some() {
+                    tracing::warn!(
+                        "⚠️ Range API ends at block {} but the VectorX contract is at block {}, checking again",
+                        range_api_end,
+                        contract_latest_block
+                    );
+                }
+                last_mismatch = mismatch;
+                reconciliation.range_api_end = Some(range_api_end);
+                reconciliation.contract_latest_block = Some(contract_latest_block);
+                reconciliation.error = None;
+            }
+            (Err(err), _) => {
+                tracing::warn!("⚠️ Cannot reconcile the range, range API error: {:?}", err);
+                reconciliation.error = Some(err.to_string());
+            }
+            (_, Err(err)) => {
+                tracing::warn!("⚠️ Cannot reconcile the range, contract error: {:?}", err);
+                reconciliation.error = Some(err.to_string());
+            }
+        }
+        *state.range_reconciliation.lock().unwrap() = reconciliation;
+    }
+}
+
+async fn fetch_contract_latest_block(state: &AppState) -> Result<u32, jsonrpsee::core::Error> {
+    let _permit = acquire_permit(&state.ethereum_permits, "Ethereum").await;
+    let result: Bytes = state
+        .ethereum_client
+        .request(
+            "eth_call",
+            rpc_params![
+                json!({
+                    "to": state.contract_address,
+                    "data": Bytes::from(latestBlockCall {}.abi_encode()),
+                }),
+                "latest"
+            ],
+        )
+        .await?;
+    latestBlockCall::abi_decode_returns(&result, true)
+        .map(|latest_block| latest_block._0)
+        .map_err(|err| {
+            jsonrpsee::core::Error::Custom(format!("Invalid latestBlock result: {}", err))
+        })
+}
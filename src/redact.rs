@@ -0,0 +1,96 @@
+//! Scrubs credentials out of text before it is logged or returned to a client.
+//!
+//! Upstream errors often echo the full RPC URL, which for paid providers carries the API key in its path, query or
+//! userinfo. URLs are therefore cut down to their scheme and host, and bearer tokens are masked.
+
+use std::io::{self, Write};
+use tracing_subscriber::fmt::MakeWriter;
+
+const REDACTED: &str = "[REDACTED]";
+
+pub fn redact(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some((start, end)) = find_url(rest) {
+        redacted.push_str(&rest[..start]);
+        redacted.push_str(&redact_url(&rest[start..end]));
+        rest = &rest[end..];
+    }
+    redacted.push_str(rest);
+    redact_bearer_tokens(&redacted)
+}
+
+/// Finds the byte range of the first `scheme://...` URL in `text`.
+fn find_url(text: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    while let Some(separator) = text[offset..].find("://") {
+        let separator = offset + separator;
+        let start = text[..separator]
+            .rfind(|c: char| !c.is_ascii_alphabetic())
+            .map_or(0, |index| index + 1);
+        if start < separator {
+            let end = text[separator..]
+                .find(|c: char| {
+                    c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | '<' | '>' | '(' | ')')
+                })
+                .map_or(text.len(), |index| separator + index);
+            return Some((start, end));
+        }
+        offset = separator + 3;
+    }
+    None
+}
+
+fn redact_url(url: &str) -> String {
+    let Some((scheme, remainder)) = url.split_once("://") else {
+        return url.to_owned();
+    };
+    let authority_end = remainder.find(['/', '?', '#']).unwrap_or(remainder.len());
+    let authority = &remainder[..authority_end];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    match &remainder[authority_end..] {
+        "" | "/" => format!("{}://{}{}", scheme, host, &remainder[authority_end..]),
+        _ => format!("{}://{}/{}", scheme, host, REDACTED),
+    }
+}
+
+fn redact_bearer_tokens(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("Bearer ") {
+        let token_start = start + "Bearer ".len();
+        redacted.push_str(&rest[..token_start]);
+        redacted.push_str(REDACTED);
+        let token_end = rest[token_start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ','))
+            .map_or(rest.len(), |index| token_start + index);
+        rest = &rest[token_end..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Log writer that redacts every formatted event before handing it to the wrapped writer, e.g. `io::stdout`.
+pub struct Redacting<W>(pub W);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = Redacting<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Redacting(self.0.make_writer())
+    }
+}
+
+impl<W: Write> Write for Redacting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .write_all(redact(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
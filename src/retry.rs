@@ -0,0 +1,149 @@
+//! Bounded retries of upstream calls.
+//!
+//! Only idempotent calls may be retried, and every call through [`retry`] says so with the [`Idempotent`] marker.
+//! Reads such as data proofs, storage proofs and beacon lookups can run any number of times. A submission might land
+//! twice if a timed out attempt actually went through, so a call that changes upstream state must call the upstream
+//! directly, running exactly once with its error reaching the caller as is.
+
+use crate::retry_budget::RetryBudget;
+use std::fmt::Debug;
+use std::future::Future;
+use std::time::Duration;
+
+/// Marks a call as safe to repeat, running it again having no effect beyond the first run.
+#[derive(Clone, Copy, Debug)]
+pub struct Idempotent;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, `0` disabling them.
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each one after it.
+    pub delay: Duration,
+}
+
+/// Runs `call` and repeats it while it fails with an error `is_transient` accepts, up to `policy.retries` times and
+/// only while the shared `budget` allows.
+pub async fn retry<T, E, F, Fut>(
+    name: &str,
+    _: Idempotent,
+    policy: RetryPolicy,
+    budget: &RetryBudget,
+    is_transient: impl Fn(&E) -> bool,
+    mut call: F,
+) -> Result<T, E>
+where
+    E: Debug,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut delay = policy.delay;
+    let mut attempt = 0;
+    budget.deposit();
+    loop {
+        match call().await {
+            Err(err) if attempt < policy.retries && is_transient(&err) => {
+                if !budget.try_withdraw() {
+                    tracing::warn!(
+                        "⚠️ Retry budget exhausted, not retrying the transient {} error: {:?}",
+                        name,
+                        err
+                    );
+                    break Err(err);
+                }
+                tracing::warn!(
+                    "⏳ Transient {} error, retrying in {:?}: {:?}",
+                    name,
+                    delay,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => break result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tokio::time::Instant;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        retries: 3,
+        delay: Duration::from_millis(100),
+    };
+
+    /// Retries `outcomes` in turn through [`retry`], returning its result, the number of attempts and the time taken.
+    async fn run(
+        policy: RetryPolicy,
+        budget: &RetryBudget,
+        outcomes: &[Result<u32, &'static str>],
+    ) -> (Result<u32, &'static str>, usize, Duration) {
+        let attempts = Cell::new(0);
+        let started = Instant::now();
+        let result = retry(
+            "test",
+            Idempotent,
+            policy,
+            budget,
+            |err: &&str| *err == "transient",
+            || {
+                let outcome = outcomes[attempts.get()];
+                attempts.set(attempts.get() + 1);
+                async move { outcome }
+            },
+        )
+        .await;
+        (result, attempts.get(), started.elapsed())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transient_errors_are_retried_with_a_doubling_delay() {
+        let outcomes = [Err("transient"), Err("transient"), Ok(7)];
+        let (result, attempts, elapsed) = run(POLICY, &RetryBudget::new(0, 0), &outcomes).await;
+        assert_eq!(result, Ok(7));
+        assert_eq!(attempts, 3);
+        assert_eq!(elapsed, Duration::from_millis(100 + 200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_last_error_is_returned_once_the_retries_are_used_up() {
+        let outcomes = [Err("transient"); 5];
+        let (result, attempts, elapsed) = run(POLICY, &RetryBudget::new(0, 0), &outcomes).await;
+        assert_eq!(result, Err("transient"));
+        assert_eq!(attempts, 4);
+        assert_eq!(elapsed, Duration::from_millis(100 + 200 + 400));
+
+        let no_retries = RetryPolicy {
+            retries: 0,
+            ..POLICY
+        };
+        let (result, attempts, _) = run(no_retries, &RetryBudget::new(0, 0), &outcomes).await;
+        assert_eq!(result, Err("transient"));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn other_errors_are_not_retried() {
+        let outcomes = [Err("transient"), Err("not found"), Ok(7)];
+        let (result, attempts, elapsed) = run(POLICY, &RetryBudget::new(0, 0), &outcomes).await;
+        assert_eq!(result, Err("not found"));
+        assert_eq!(attempts, 2);
+        assert_eq!(elapsed, Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn an_exhausted_budget_stops_the_retries() {
+        // A tenth of a token per first attempt and no burst, so not even one retry is affordable.
+        let budget = RetryBudget::new(10, 0);
+        let outcomes = [Err("transient"), Ok(7)];
+        let (result, attempts, elapsed) = run(POLICY, &budget, &outcomes).await;
+        assert_eq!(result, Err("transient"));
+        assert_eq!(attempts, 1);
+        assert_eq!(elapsed, Duration::ZERO);
+    }
+}
@@ -0,0 +1,93 @@
+//! Token bucket shared by all requests that caps the share of upstream calls being retried, so that during an
+//! upstream brownout our own retries don't pile more load onto it.
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+pub struct RetryBudget {
+    /// Tokens credited per first attempt, a retry costing one.
+    ratio: f64,
+    burst: f64,
+    state: Mutex<BudgetState>,
+}
+
+struct BudgetState {
+    balance: f64,
+    attempts: u64,
+    retries: u64,
+    skipped_retries: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryBudgetStatus {
+    enabled: bool,
+    percent: f64,
+    /// Share of the burst allowance in use, `1` meaning retries are being skipped.
+    utilization: f64,
+    attempts: u64,
+    retries: u64,
+    skipped_retries: u64,
+}
+
+impl RetryBudget {
+    /// Allows retrying `percent` of first attempts on top of an allowance of `burst` retries, which the bucket starts
+    /// with. A `percent` of `0` leaves retries unbudgeted.
+    pub fn new(percent: u32, burst: u32) -> Self {
+        RetryBudget {
+            ratio: f64::from(percent) / 100.0,
+            burst: f64::from(burst),
+            state: Mutex::new(BudgetState {
+                balance: f64::from(burst),
+                attempts: 0,
+                retries: 0,
+                skipped_retries: 0,
+            }),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.ratio > 0.0
+    }
+
+    /// Credits the budget for a first attempt.
+    pub fn deposit(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.attempts += 1;
+        state.balance = (state.balance + self.ratio).min(self.burst.max(self.ratio));
+    }
+
+    /// Takes a token for a retry, returning false when the budget is exhausted and the retry should be skipped.
+    pub fn try_withdraw(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if self.is_enabled() && state.balance < 1.0 {
+            state.skipped_retries += 1;
+            return false;
+        }
+        state.balance -= 1.0;
+        state.retries += 1;
+        true
+    }
+
+    /// Whether retries are being skipped, the whole burst allowance being in use.
+    pub fn is_exhausted(&self) -> bool {
+        self.status().utilization >= 1.0
+    }
+
+    pub fn status(&self) -> RetryBudgetStatus {
+        let state = self.state.lock().unwrap();
+        let capacity = self.burst.max(self.ratio);
+        RetryBudgetStatus {
+            enabled: self.is_enabled(),
+            percent: self.ratio * 100.0,
+            utilization: if self.is_enabled() && capacity > 0.0 {
+                (1.0 - state.balance / capacity).clamp(0.0, 1.0)
+            } else {
+                0.0
+            },
+            attempts: state.attempts,
+            retries: state.retries,
+            skipped_retries: state.skipped_retries,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Routes JSON-RPC requests through reqwest when an outbound proxy is configured, since the jsonrpsee HTTP client
+//! connects to the node directly and has no proxy support of its own.
+
+use crate::upstream;
+use hyper::{Body, Request, Response};
+use jsonrpsee::http_client::transport::{Error, HttpBackend};
+use jsonrpsee::http_client::HttpClient;
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Variables reqwest reads its proxies from, `NO_PROXY` then exempting hosts per request.
+const PROXY_VARS: [&str; 6] = [
+    "HTTP_PROXY",
+    "http_proxy",
+    "HTTPS_PROXY",
+    "https_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
+pub type RpcClient = HttpClient<ProxiedBackend>;
+
+#[derive(Clone)]
+pub struct RpcProxyLayer {
+    client: Option<reqwest::Client>,
+}
+
+impl RpcProxyLayer {
+    /// Sends requests with `client` when any proxy variable is set, and with jsonrpsee's own backend otherwise.
+    pub fn from_env(client: &reqwest::Client) -> Self {
+        let proxied = PROXY_VARS
+            .iter()
+            .any(|name| env::var_os(name).is_some_and(|value| !value.is_empty()));
+        RpcProxyLayer {
+            client: proxied.then(|| client.clone()),
+        }
+    }
+
+    pub fn is_proxied(&self) -> bool {
+        self.client.is_some()
+    }
+}
+
+impl Layer<HttpBackend> for RpcProxyLayer {
+    type Service = ProxiedBackend;
+
+    fn layer(&self, backend: HttpBackend) -> Self::Service {
+        ProxiedBackend {
+            backend,
+            client: self.client.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ProxiedBackend {
+    backend: HttpBackend,
+    client: Option<reqwest::Client>,
+}
+
+impl Service<Request<Body>> for ProxiedBackend {
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.client {
+            Some(_) => Poll::Ready(Ok(())),
+            None => self.backend.poll_ready(cx),
+        }
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if let Some(host) = request.uri().host() {
+            upstream::record_host(host);
+        }
+        let Some(client) = self.client.clone() else {
+            return self.backend.call(request);
+        };
+        // jsonrpsee checks the status code and the response size limit itself once it gets the response back.
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body)
+                .await
+                .map_err(|err| Error::Http(Box::new(err)))?;
+            let response = client
+                .request(parts.method, parts.uri.to_string())
+                .headers(parts.headers)
+                .body(body)
+                .send()
+                .await
+                .map_err(|err| Error::Http(Box::new(err)))?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response
+                .bytes()
+                .await
+                .map_err(|err| Error::Http(Box::new(err)))?;
+            let mut response = Response::new(Body::from(body));
+            *response.status_mut() = status;
+            *response.headers_mut() = headers;
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,415 @@
+//! Serves connections with hyper directly, since `axum::serve` doesn't expose the server-side limits against slow
+//! clients.
+
+use axum::extract::{ConnectInfo, Request};
+use axum::Router;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto::Builder,
+    server::graceful::GracefulShutdown,
+    service::TowerToHyperService,
+};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::{Instant, Sleep};
+use tower::ServiceExt;
+
+/// Pause after a failed accept, e.g. when out of file descriptors, before accepting again.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+/// Written to connections that started a request but didn't finish its headers in time.
+const REQUEST_TIMEOUT_RESPONSE: &[u8] =
+    b"HTTP/1.1 408 Request Timeout\r\nconnection: close\r\ncontent-length: 0\r\n\r\n";
+/// Start of the HTTP/2 connection preface, which the timeouts below don't apply to.
+const HTTP2_PREFACE_START: &[u8] = b"PRI ";
+
+#[derive(Clone, Copy)]
+pub struct ServerConfig {
+    /// Time a client has to send the rest of the request headers once it sent their first byte. Clients that don't
+    /// make it get a `408` and the connection is closed.
+    pub header_read_timeout: Duration,
+    /// Time a connection can sit without sending or receiving anything, before its first request or between
+    /// kept-alive requests, before it is closed.
+    pub idle_timeout: Duration,
+    /// Time the open connections get to finish their in-flight requests once the server is stopping.
+    pub shutdown_grace: Duration,
+}
+
+/// Accepts TCP connections until SIGINT or SIGTERM, serving each with [`serve_connection`], then [`drain`]s them.
+pub async fn serve(listener: TcpListener, app: Router, config: ServerConfig) -> io::Result<()> {
+    let connections = GracefulShutdown::new();
+    let accept_loop = async {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    serve_connection(stream, app.clone(), config, Some(peer), &connections)
+                }
+                Err(err) => log_accept_error(err).await,
+            }
+        }
+    };
+    let result = tokio::select! {
+        _ = accept_loop => Ok(()),
+        result = shutdown_signal() => result,
+    };
+    drain(connections, config.shutdown_grace).await;
+    result
+}
+
+/// Serves HTTP/1 and HTTP/2 requests on `io` in a new task watched by `connections`, handing the requests the `peer`
+/// address, if any, as [`ConnectInfo`].
+pub fn serve_connection<I>(
+    io: I,
+    app: Router,
+    config: ServerConfig,
+    peer: Option<SocketAddr>,
+    connections: &GracefulShutdown,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let requests = Arc::new(Requests::default());
+    let io = Timeouts::new(io, config, requests.clone());
+    let service = TowerToHyperService::new(
+        app.map_request(move |mut request: Request<_>| {
+            if let Some(peer) = peer {
+                request.extensions_mut().insert(ConnectInfo(peer));
+            }
+            request
+        })
+        .map_future(move |response| {
+            let in_flight = InFlight::start(requests.clone());
+            async move {
+                let response = response.await;
+                drop(in_flight);
+                response
+            }
+        }),
+    );
+    // Not hyper's own header read timeout, which also runs while a connection is idle and closes it without a
+    // response.
+    let connection = Builder::new(TokioExecutor::new())
+        .serve_connection(TokioIo::new(io), service)
+        .into_owned();
+    let connection = connections.watch(connection);
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            tracing::debug!("Connection closed with an error: {:?}", err);
+        }
+    });
+}
+
+/// Closes the `connections` as soon as their in-flight requests are answered, waiting at most `grace` for them.
+pub async fn drain(connections: GracefulShutdown, grace: Duration) {
+    tracing::info!("🛑 Shutting down, finishing the in-flight requests");
+    if tokio::time::timeout(grace, connections.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "⚠️ Requests still running after {}s, dropping their connections",
+            grace.as_secs()
+        );
+    }
+}
+
+/// Requests of a connection whose headers were read, counted by the service and watched by [`Timeouts`].
+#[derive(Default)]
+struct Requests {
+    started: AtomicUsize,
+    in_flight: AtomicUsize,
+}
+
+/// A request being handled, until its response future completes or is dropped.
+struct InFlight(Arc<Requests>);
+
+impl InFlight {
+    fn start(requests: Arc<Requests>) -> Self {
+        requests.started.fetch_add(1, Ordering::Relaxed);
+        requests.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(requests)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Connection IO enforcing [`ServerConfig::header_read_timeout`] and [`ServerConfig::idle_timeout`] on HTTP/1
+/// connections, which hyper doesn't tell apart.
+struct Timeouts<I> {
+    io: I,
+    config: ServerConfig,
+    requests: Arc<Requests>,
+    /// `requests.started` when last looked at, a change meaning the headers being read were complete.
+    seen_started: usize,
+    /// When the first byte of headers still being read arrived.
+    head_started: Option<Instant>,
+    last_activity: Instant,
+    read_any: bool,
+    /// HTTP/2 connections are left to hyper.
+    disabled: bool,
+    timer: Pin<Box<Sleep>>,
+}
+
+impl<I> Timeouts<I> {
+    fn new(io: I, config: ServerConfig, requests: Arc<Requests>) -> Self {
+        let now = Instant::now();
+        Timeouts {
+            io,
+            config,
+            requests,
+            seen_started: 0,
+            head_started: None,
+            last_activity: now,
+            read_any: false,
+            disabled: false,
+            timer: Box::pin(tokio::time::sleep_until(now + config.idle_timeout)),
+        }
+    }
+
+    /// Whether a request is being handled, forgetting the headers being read once the service got them.
+    fn busy(&mut self) -> bool {
+        let started = self.requests.started.load(Ordering::Relaxed);
+        if started != self.seen_started {
+            self.seen_started = started;
+            self.head_started = None;
+        }
+        self.requests.in_flight.load(Ordering::Relaxed) > 0
+    }
+}
+
+impl<I: AsyncRead + AsyncWrite + Unpin> AsyncRead for Timeouts<I> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        if let Poll::Ready(result) = Pin::new(&mut self.io).poll_read(cx, buf) {
+            let read = &buf.filled()[filled..];
+            if !read.is_empty() {
+                let now = Instant::now();
+                self.last_activity = now;
+                if !self.read_any {
+                    self.read_any = true;
+                    self.disabled = read.starts_with(HTTP2_PREFACE_START);
+                }
+                if !self.busy() && self.head_started.is_none() {
+                    self.head_started = Some(now);
+                }
+            }
+            return Poll::Ready(result);
+        }
+        if self.disabled {
+            return Poll::Pending;
+        }
+        // Request bodies and slow handlers are left to the request timeout.
+        if self.busy() {
+            return Poll::Pending;
+        }
+        let deadline = match self.head_started {
+            Some(head_started) => head_started + self.config.header_read_timeout,
+            None => self.last_activity + self.config.idle_timeout,
+        };
+        if self.timer.deadline() != deadline {
+            self.timer.as_mut().reset(deadline);
+        }
+        ready!(self.timer.as_mut().poll(cx));
+        if self.head_started.is_none() {
+            // Reads as the client closing the connection, which hyper handles quietly between requests.
+            return Poll::Ready(Ok(()));
+        }
+        // Best effort, the response fits in any socket buffer and the connection is closed either way.
+        let _ = Pin::new(&mut self.io).poll_write(cx, REQUEST_TIMEOUT_RESPONSE);
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "request headers not received in time",
+        )))
+    }
+}
+
+impl<I: AsyncWrite + Unpin> AsyncWrite for Timeouts<I> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.io).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = result {
+            if written > 0 {
+                self.last_activity = Instant::now();
+            }
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+/// Resolves on SIGINT, or on SIGTERM as sent by orchestrators stopping the service.
+pub async fn shutdown_signal() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut sigterm = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = sigterm.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+pub async fn log_accept_error(err: io::Error) {
+    tracing::error!("❌ Cannot accept a connection: {:?}", err);
+    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    const CONFIG: ServerConfig = ServerConfig {
+        header_read_timeout: Duration::from_millis(100),
+        idle_timeout: Duration::from_millis(300),
+        shutdown_grace: Duration::from_millis(500),
+    };
+    const REQUEST: &[u8] = b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n";
+
+    /// Opens a connection to an app answering `/` right away and `/slow` after 200ms, the connection closing when
+    /// `connections` is dropped.
+    fn connect_to(connections: &GracefulShutdown) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(4096);
+        let app = Router::new().route("/", get(|| async { "ok" })).route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                "ok"
+            }),
+        );
+        serve_connection(server, app, CONFIG, None, connections);
+        client
+    }
+
+    fn connect() -> (DuplexStream, GracefulShutdown) {
+        let connections = GracefulShutdown::new();
+        (connect_to(&connections), connections)
+    }
+
+    async fn read_response(client: &mut DuplexStream) -> String {
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\nok") {
+            let mut chunk = [0; 1024];
+            let read = client.read(&mut chunk).await.unwrap();
+            assert_ne!(
+                read,
+                0,
+                "closed after {:?}",
+                String::from_utf8_lossy(&response)
+            );
+            response.extend_from_slice(&chunk[..read]);
+        }
+        String::from_utf8(response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn incomplete_headers_get_a_408() {
+        let (mut client, _connections) = connect();
+        let started = Instant::now();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response.as_bytes(), REQUEST_TIMEOUT_RESPONSE);
+        assert!(started.elapsed() >= CONFIG.header_read_timeout);
+        assert!(started.elapsed() < CONFIG.idle_timeout);
+    }
+
+    #[tokio::test]
+    async fn idle_connections_are_closed_without_a_response() {
+        let (mut client, _connections) = connect();
+        let started = Instant::now();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response, "");
+        assert!(started.elapsed() >= CONFIG.idle_timeout);
+    }
+
+    #[tokio::test]
+    async fn kept_alive_connections_outlive_the_header_read_timeout() {
+        let (mut client, _connections) = connect();
+        client.write_all(REQUEST).await.unwrap();
+        assert!(read_response(&mut client)
+            .await
+            .starts_with("HTTP/1.1 200 OK\r\n"));
+
+        tokio::time::sleep(CONFIG.header_read_timeout * 2).await;
+        client.write_all(REQUEST).await.unwrap();
+        assert!(read_response(&mut client)
+            .await
+            .starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let idle_since = Instant::now();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response, "");
+        assert!(idle_since.elapsed() >= CONFIG.idle_timeout - CONFIG.header_read_timeout);
+    }
+
+    #[tokio::test]
+    async fn draining_finishes_the_in_flight_requests() {
+        let connections = GracefulShutdown::new();
+        let mut busy = connect_to(&connections);
+        let mut idle = connect_to(&connections);
+        busy.write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        let drained = tokio::spawn(drain(connections, CONFIG.shutdown_grace));
+        let mut response = String::new();
+        idle.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response, "");
+        let response = read_response(&mut busy).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        drained.await.unwrap();
+        assert!(started.elapsed() < CONFIG.shutdown_grace);
+    }
+
+    #[tokio::test]
+    async fn draining_gives_up_after_the_grace() {
+        let connections = GracefulShutdown::new();
+        let mut client = connect_to(&connections);
+        client
+            .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let started = Instant::now();
+        drain(connections, Duration::from_millis(50)).await;
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(started.elapsed() < Duration::from_millis(150));
+    }
+}
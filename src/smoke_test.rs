@@ -0,0 +1,212 @@
+//! One-shot check of every endpoint against the configured upstreams, run with `--smoke-test`.
+
+use crate::{
+    alive, debug_routes, get_avl_bridged, get_avl_head, get_avl_proof, get_beacon_slot,
+    get_eth_head, get_eth_proof, get_health_summary, info, router, routes, AppState, AvlProofQuery,
+    BeaconSlotQuery, EndpointTimeouts, IndexStruct, NodeEncoding, ProofFormat,
+};
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, Method, Request, StatusCode};
+use axum::response::IntoResponse;
+use std::env;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use tower::ServiceExt;
+
+/// Calls every handler once and prints a summary, returning whether all of the checks passed.
+///
+/// Endpoints taking a block, message or slot are only checked when the matching `SMOKE_TEST_*` variable is set.
+pub async fn run(state: Arc<AppState>) -> bool {
+    let mut passed = check_routing(state.clone()).await;
+    passed &= check("/", alive(State(state.clone()))).await;
+    passed &= check("/info", info(State(state.clone()))).await;
+    passed &= check("/eth/head", get_eth_head(State(state.clone()))).await;
+    passed &= check("/avl/head", get_avl_head(State(state.clone()))).await;
+    passed &= check("/health/summary", get_health_summary(State(state.clone()))).await;
+
+    match (
+        parse_var("SMOKE_TEST_BLOCK_HASH"),
+        parse_var("SMOKE_TEST_INDEX"),
+    ) {
+        (Some(Ok(block_hash)), Some(Ok(index))) => {
+            passed &= check(
+                "/eth/proof",
+                get_eth_proof(
+                    Path(block_hash),
+                    IndexStruct {
+                        index,
+                        format: ProofFormat::Json,
+                        wait: false,
+                        timeout: None,
+                        partial: false,
+                        fresh: false,
+                        range_hash: None,
+                    },
+                    HeaderMap::new(),
+                    State(state.clone()),
+                ),
+            )
+            .await;
+            passed &= check(
+                "/avl/bridged",
+                get_avl_bridged(Path(block_hash), State(state.clone())),
+            )
+            .await;
+        }
+        (Some(Err(err)), _) | (_, Some(Err(err))) => passed &= invalid("/eth/proof", err),
+        _ => skip("/eth/proof", "SMOKE_TEST_BLOCK_HASH and SMOKE_TEST_INDEX"),
+    }
+
+    match (
+        parse_var("SMOKE_TEST_ETH_BLOCK_HASH"),
+        parse_var("SMOKE_TEST_MESSAGE_ID"),
+    ) {
+        (Some(Ok(block_hash)), Some(Ok(message_id))) => {
+            passed &= check(
+                "/avl/proof",
+                get_avl_proof(
+                    Path((block_hash, message_id)),
+                    Query(AvlProofQuery {
+                        slot_offset: 0,
+                        slots: None,
+                        min_confirmations: None,
+                        encoding: NodeEncoding::Hex,
+                    }),
+                    HeaderMap::new(),
+                    State(state.clone()),
+                ),
+            )
+            .await;
+        }
+        (Some(Err(err)), _) | (_, Some(Err(err))) => passed &= invalid("/avl/proof", err),
+        _ => skip(
+            "/avl/proof",
+            "SMOKE_TEST_ETH_BLOCK_HASH and SMOKE_TEST_MESSAGE_ID",
+        ),
+    }
+
+    match parse_var("SMOKE_TEST_SLOT") {
+        Some(Ok(slot)) => {
+            passed &= check(
+                "/beacon/slot",
+                get_beacon_slot(
+                    Path(slot),
+                    Query(BeaconSlotQuery {
+                        verbose: false,
+                        fields: None,
+                    }),
+                    State(state),
+                ),
+            )
+            .await;
+        }
+        Some(Err(err)) => passed &= invalid("/beacon/slot", err),
+        None => skip("/beacon/slot", "SMOKE_TEST_SLOT"),
+    }
+
+    println!(
+        "{}",
+        if passed {
+            "✅ Smoke test passed"
+        } else {
+            "❌ Smoke test failed"
+        }
+    );
+    passed
+}
+
+/// Checks the routing table without reaching the upstreams: a `POST` to every registered path has to be refused with
+/// `405`, which shows it is routed and only takes `GET`, while an unknown path has to get a `404`.
+async fn check_routing(state: Arc<AppState>) -> bool {
+    let mut routes = routes(&EndpointTimeouts::default(), None);
+    routes.extend(debug_routes());
+    let paths: Vec<String> = routes
+        .iter()
+        .map(|(endpoint, _)| {
+            endpoint
+                .path
+                .split('/')
+                .map(|segment| {
+                    if segment.starts_with(':') {
+                        "0"
+                    } else {
+                        segment
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .collect();
+    let app = router(routes).with_state(state);
+    let mut requests: Vec<(Method, String, StatusCode)> = paths
+        .into_iter()
+        .map(|path| (Method::POST, path, StatusCode::METHOD_NOT_ALLOWED))
+        .collect();
+    requests.push((
+        Method::GET,
+        "/no/such/route".to_owned(),
+        StatusCode::NOT_FOUND,
+    ));
+    let mut passed = true;
+    for (method, path, expected) in requests {
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(&path)
+            .body(Body::empty())
+            .unwrap();
+        let status = app.clone().oneshot(request).await.unwrap().status();
+        if status != expected {
+            println!(
+                "❌ {:<14} {} {} answered {} instead of {}",
+                "routing", method, path, status, expected
+            );
+            passed = false;
+        }
+    }
+    if passed {
+        println!("✅ {:<14} every route is wired", "routing");
+    }
+    passed
+}
+
+async fn check<R: IntoResponse>(name: &str, handler: impl Future<Output = R>) -> bool {
+    let started = Instant::now();
+    let response = handler.await.into_response();
+    let elapsed = started.elapsed();
+    let status = response.status();
+    if status.is_success() {
+        println!("✅ {:<14} {} in {:?}", name, status, elapsed);
+        return true;
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap_or_default();
+    println!(
+        "❌ {:<14} {} in {:?}: {}",
+        name,
+        status,
+        elapsed,
+        String::from_utf8_lossy(&body)
+    );
+    false
+}
+
+fn parse_var<T: FromStr>(name: &str) -> Option<Result<T, String>> {
+    env::var(name).ok().map(|value| {
+        value
+            .parse()
+            .map_err(|_| format!("{} has an invalid value {:?}", name, value))
+    })
+}
+
+fn invalid(name: &str, err: String) -> bool {
+    println!("❌ {:<14} {}", name, err);
+    false
+}
+
+fn skip(name: &str, variables: &str) {
+    println!("⏭️ {:<14} skipped, set {} to check it", name, variables);
+}
@@ -0,0 +1,119 @@
+//! Configuration parsing for startup, reporting which setting is wrong instead of panicking.
+
+use crate::redact::redact;
+use crate::rpc_proxy::{RpcClient, RpcProxyLayer};
+use jsonrpsee::http_client::HttpClientBuilder;
+use std::env;
+use std::fmt::{self, Display};
+use std::io;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum StartupError {
+    UnknownProfile(String),
+    InvalidVar {
+        name: &'static str,
+        value: String,
+        reason: String,
+    },
+    Client {
+        name: &'static str,
+        url: String,
+        reason: String,
+    },
+    HttpClient(reqwest::Error),
+    ProofStore {
+        path: String,
+        reason: io::Error,
+    },
+    Listen {
+        address: String,
+        reason: io::Error,
+    },
+    ZstdDictionary {
+        path: String,
+        reason: io::Error,
+    },
+    ChainVerification(String),
+    /// Contracts that aren't known deployments, with `STRICT_CONTRACT_CHECK` on.
+    UnknownContracts(usize),
+    SmokeTest,
+}
+
+impl Display for StartupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartupError::UnknownProfile(profile) => write!(
+                f,
+                "Unknown ENV_PROFILE {:?}, expected testnet, mainnet or local",
+                profile
+            ),
+            StartupError::InvalidVar {
+                name,
+                value,
+                reason,
+            } => write!(f, "Invalid {} {:?}: {}", name, value, reason),
+            StartupError::Client { name, url, reason } => write!(
+                f,
+                "Cannot build the client for {}: {}, URL {}",
+                name,
+                redact(reason),
+                redact(url)
+            ),
+            StartupError::HttpClient(err) => write!(f, "Cannot build the HTTP client: {}", err),
+            StartupError::ProofStore { path, reason } => write!(
+                f,
+                "Cannot open the proof store at PROOF_STORE_PATH {:?}: {}",
+                path, reason
+            ),
+            StartupError::Listen { address, reason } => {
+                write!(f, "Cannot listen on {}: {}", address, reason)
+            }
+            StartupError::ZstdDictionary { path, reason } => write!(
+                f,
+                "Cannot load the zstd dictionary at ZSTD_DICTIONARY_PATH {:?}: {}",
+                path, reason
+            ),
+            StartupError::ChainVerification(reason) => {
+                write!(f, "Chain verification failed: {}", reason)
+            }
+            StartupError::UnknownContracts(count) => write!(
+                f,
+                "{} configured contracts are not known deployments and STRICT_CONTRACT_CHECK is on",
+                count
+            ),
+            StartupError::SmokeTest => write!(f, "Smoke test failed"),
+        }
+    }
+}
+
+/// Parses the variable `name`, falling back to `default` when it is unset.
+pub fn env_or<T: FromStr>(name: &'static str, default: T) -> Result<T, StartupError>
+where
+    T::Err: Display,
+{
+    match env::var(name) {
+        Ok(value) => value.parse::<T>().map_err(|err| StartupError::InvalidVar {
+            name,
+            reason: err.to_string(),
+            value,
+        }),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Builds a JSON-RPC client for the URL configured in `name`.
+pub fn rpc_client(
+    name: &'static str,
+    url: String,
+    proxy: &RpcProxyLayer,
+) -> Result<RpcClient, StartupError> {
+    HttpClientBuilder::default()
+        .set_middleware(tower::ServiceBuilder::new().layer(proxy.clone()))
+        .build(&url)
+        .map_err(|err| StartupError::Client {
+            name,
+            reason: err.to_string(),
+            url,
+        })
+}
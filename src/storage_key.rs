@@ -0,0 +1,127 @@
+//! Substrate storage key construction, so pallet items are addressed by name rather than by hand-built hex.
+
+use alloy_primitives::hex;
+use sp_io::hashing::{blake2_128, twox_128, twox_64};
+use std::str::FromStr;
+
+/// How a storage map hashes its key before appending it to the item prefix.
+#[derive(Clone, Copy)]
+pub enum StorageHasher {
+    Identity,
+    Twox64Concat,
+    Blake2_128Concat,
+}
+
+impl StorageHasher {
+    fn hash(self, key: &[u8]) -> Vec<u8> {
+        match self {
+            StorageHasher::Identity => key.to_vec(),
+            StorageHasher::Twox64Concat => [twox_64(key).as_slice(), key].concat(),
+            StorageHasher::Blake2_128Concat => [blake2_128(key).as_slice(), key].concat(),
+        }
+    }
+}
+
+/// Parses the hasher names used in pallet metadata, e.g. `blake2_128Concat`.
+impl FromStr for StorageHasher {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "identity" => Ok(StorageHasher::Identity),
+            "twox64Concat" => Ok(StorageHasher::Twox64Concat),
+            "blake2_128Concat" => Ok(StorageHasher::Blake2_128Concat),
+            _ => Err(format!(
+                "Unknown hasher {:?}, valid hashers are identity, twox64Concat and blake2_128Concat",
+                name
+            )),
+        }
+    }
+}
+
+/// Key of a plain storage value, `twox128(pallet) ++ twox128(item)`.
+pub fn plain(pallet: &str, item: &str) -> String {
+    format!("0x{}", hex::encode(prefix(pallet, item)))
+}
+
+/// Key of a storage map entry, the item prefix followed by the hashed, SCALE encoded map key.
+pub fn map(pallet: &str, item: &str, hasher: StorageHasher, key: &[u8]) -> String {
+    let mut storage_key = prefix(pallet, item);
+    storage_key.extend(hasher.hash(key));
+    format!("0x{}", hex::encode(storage_key))
+}
+
+fn prefix(pallet: &str, item: &str) -> Vec<u8> {
+    [twox_128(pallet.as_bytes()), twox_128(item.as_bytes())].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keys as read from an Avail node's `state_getKeys` and the polkadot.js storage explorer.
+
+    #[test]
+    fn plain_keys_match_the_node() {
+        assert_eq!(
+            plain("System", "Number"),
+            "0x26aa394eea5630e07c48ae0c9558cef702a5c1b19ab7a04f536c519aca4983ac"
+        );
+        assert_eq!(
+            plain("Timestamp", "Now"),
+            "0xf0c365c3cf59d671eb72da0e7a4113c49f1f0515f462cdcf84e0f1d6045dfcbb"
+        );
+    }
+
+    #[test]
+    fn blake2_128_concat_key_matches_the_node() {
+        // System.Account of the development account Alice.
+        let alice = hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d")
+            .unwrap();
+        assert_eq!(
+            map("System", "Account", StorageHasher::Blake2_128Concat, &alice),
+            "0x26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9de1e86a9a8c739864cf3cc5ec2bea59fd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+        );
+    }
+
+    #[test]
+    fn twox_64_concat_key_matches_the_node() {
+        // System.BlockHash of the genesis block, keyed by the SCALE encoded block number 0.
+        assert_eq!(
+            map(
+                "System",
+                "BlockHash",
+                StorageHasher::Twox64Concat,
+                &0u32.to_le_bytes()
+            ),
+            "0x26aa394eea5630e07c48ae0c9558cef7a44704b568d21667356a5a050c118746b4def25cfda6ef3a00000000"
+        );
+    }
+
+    #[test]
+    fn identity_key_appends_the_key_as_is() {
+        assert_eq!(
+            map(
+                "Vector",
+                "Timestamps",
+                StorageHasher::Identity,
+                &1u64.to_le_bytes()
+            ),
+            format!("{}0100000000000000", plain("Vector", "Timestamps"))
+        );
+    }
+
+    #[test]
+    fn parses_the_metadata_hasher_names() {
+        assert!(matches!(
+            "blake2_128Concat".parse(),
+            Ok(StorageHasher::Blake2_128Concat)
+        ));
+        assert!(matches!(
+            "twox64Concat".parse(),
+            Ok(StorageHasher::Twox64Concat)
+        ));
+        assert!(matches!("identity".parse(), Ok(StorageHasher::Identity)));
+        assert!("blake2_256".parse::<StorageHasher>().is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Describes where the bridge contract stores a message, so a contract upgrade that moves it is a config change.
+
+use crate::hashing::HashAlgorithm;
+use alloy_primitives::U256;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Base slot of the contract's state variable and the steps leading from it to the message's first slot, written as
+/// `1/mapping(id)` for a `mapping(uint256 => ...)` at slot 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageLayout {
+    base_slot: U256,
+    steps: Vec<LayoutStep>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum LayoutStep {
+    /// Entry of a mapping at the current slot, keyed by the message id: `hash(key ++ slot)`.
+    MappingById,
+    /// Entry of a mapping at the current slot under a fixed key, e.g. a version of a nested mapping.
+    Mapping(U256),
+    /// Element of a dynamic array at the current slot indexed by the message id, elements taking the given number of
+    /// slots: `hash(slot) + id * slots`.
+    Array(U256),
+    /// Field of a struct starting at the current slot.
+    Offset(U256),
+}
+
+impl Default for StorageLayout {
+    /// The bridge contract's mapping of messages at slot 1, keyed by message id.
+    fn default() -> Self {
+        StorageLayout {
+            base_slot: U256::from(1),
+            steps: vec![LayoutStep::MappingById],
+        }
+    }
+}
+
+impl StorageLayout {
+    /// First slot of the message, following the steps the way Solidity lays out storage. The EVM always derives slots
+    /// with keccak256, whatever hash the bridge's trees use.
+    pub fn slot(&self, message_id: U256) -> U256 {
+        let hash = HashAlgorithm::Keccak256;
+        self.steps
+            .iter()
+            .fold(self.base_slot, |slot, step| match step {
+                LayoutStep::MappingById => mapping_slot(message_id, slot, hash),
+                LayoutStep::Mapping(key) => mapping_slot(*key, slot, hash),
+                LayoutStep::Array(element_slots) => {
+                    U256::from_be_slice(hash.hash(&slot.to_be_bytes::<32>()).as_slice())
+                        .wrapping_add(message_id.wrapping_mul(*element_slots))
+                }
+                LayoutStep::Offset(offset) => slot.wrapping_add(*offset),
+            })
+    }
+}
+
+/// Slot of a mapping entry, `keccak256(abi.encode(key, slot))`. `abi.encode` writes each `uint256` as a 32 byte big-endian
+/// word, left-padded with zeros, so a key of 1 is 31 zero bytes followed by `0x01`. A shorter or unpadded encoding
+/// would still hash to a slot, just not the contract's, which is why the tests pin it to slots computed by Solidity.
+fn mapping_slot(key: U256, slot: U256, hash: HashAlgorithm) -> U256 {
+    let encoded = [key.to_be_bytes::<32>(), slot.to_be_bytes::<32>()].concat();
+    U256::from_be_slice(hash.hash(&encoded).as_slice())
+}
+
+impl FromStr for StorageLayout {
+    type Err = String;
+
+    fn from_str(layout: &str) -> Result<Self, Self::Err> {
+        let mut parts = layout.split('/').map(str::trim);
+        let base_slot = parts
+            .next()
+            .unwrap_or_default()
+            .parse::<U256>()
+            .map_err(|_| {
+                "the layout must start with the base slot, e.g. 1/mapping(id)".to_owned()
+            })?;
+        let steps = parts
+            .map(|part| {
+                let (name, argument) = part
+                    .strip_suffix(')')
+                    .and_then(|part| part.split_once('('))
+                    .ok_or_else(|| format!("step {:?} must be written as name(argument)", part))?;
+                let number = || {
+                    argument
+                        .trim()
+                        .parse::<U256>()
+                        .map_err(|_| format!("step {:?} needs a number", part))
+                };
+                match name.trim() {
+                    "mapping" if argument.trim() == "id" => Ok(LayoutStep::MappingById),
+                    "mapping" => number().map(LayoutStep::Mapping),
+                    "array" => number().map(LayoutStep::Array),
+                    "offset" => number().map(LayoutStep::Offset),
+                    _ => Err(format!(
+                        "Unknown step {:?}, valid steps are mapping(id), mapping(key), array(slots) and offset(slots)",
+                        part
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !steps
+            .iter()
+            .any(|step| matches!(step, LayoutStep::MappingById | LayoutStep::Array(_)))
+        {
+            return Err(
+                "the layout must use the message id in a mapping(id) or array step".to_owned(),
+            );
+        }
+        Ok(StorageLayout { base_slot, steps })
+    }
+}
+
+impl Display for StorageLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base_slot)?;
+        for step in &self.steps {
+            match step {
+                LayoutStep::MappingById => write!(f, "/mapping(id)")?,
+                LayoutStep::Mapping(key) => write!(f, "/mapping({})", key)?,
+                LayoutStep::Array(element_slots) => write!(f, "/array({})", element_slots)?,
+                LayoutStep::Offset(offset) => write!(f, "/offset({})", offset)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{keccak256, B256};
+
+    fn word(value: u64) -> [u8; 32] {
+        U256::from(value).to_be_bytes()
+    }
+
+    fn keccak_slot(data: &[u8]) -> U256 {
+        U256::from_be_bytes(keccak256(data).0)
+    }
+
+    fn layout(layout: &str) -> StorageLayout {
+        layout.parse().unwrap()
+    }
+
+    /// `keccak256(abi.encode(messageId, 1))` as computed by Solidity, for small and near-max message ids.
+    const ABI_ENCODING_VECTORS: [(U256, &str); 5] = [
+        (
+            U256::ZERO,
+            "0xa6eef7e35abe7026729641147f7915573c7e97b47efa546f5f6e3230263bcb49",
+        ),
+        (
+            U256::from_limbs([1, 0, 0, 0]),
+            "0xcc69885fda6bcc1a4ace058b4a62bf5e179ea78fd58a1ccd71c22cc9b688792f",
+        ),
+        (
+            U256::from_limbs([u64::MAX, 0, 0, 0]),
+            "0xf96a472bdfe5b2a759c12ef9eaac602e223d6505b9b6a2b2dcce10ef8c8be639",
+        ),
+        (
+            U256::from_limbs([u64::MAX - 1, u64::MAX, u64::MAX, u64::MAX]),
+            "0x84a2b82f3835a09091553cc981cd434c85d4f7a608cb171945a84757cfbaad3c",
+        ),
+        (
+            U256::MAX,
+            "0xc39d774f18115b85b81494d65e588b565d73abc969333d1da7b0a0eb0729accd",
+        ),
+    ];
+
+    #[test]
+    fn mapping_by_id_hashes_the_id_with_the_slot() {
+        let expected = keccak_slot(&[word(5), word(1)].concat());
+        assert_eq!(StorageLayout::default().slot(U256::from(5)), expected);
+        assert_eq!(layout("1/mapping(id)"), StorageLayout::default());
+    }
+
+    #[test]
+    fn mapping_keys_are_abi_encoded_like_solidity() {
+        for (message_id, expected) in ABI_ENCODING_VECTORS {
+            let slot = StorageLayout::default().slot(message_id);
+            assert_eq!(
+                B256::from(slot).to_string(),
+                expected,
+                "message id {}",
+                message_id
+            );
+        }
+    }
+
+    #[test]
+    fn mapping_with_a_fixed_key_nests_the_next_step() {
+        let version_slot = keccak_slot(&[word(7), word(2)].concat());
+        let expected =
+            keccak_slot(&[word(5).as_slice(), &version_slot.to_be_bytes::<32>()].concat());
+        assert_eq!(
+            layout("2/mapping(7)/mapping(id)").slot(U256::from(5)),
+            expected
+        );
+    }
+
+    #[test]
+    fn array_indexes_from_the_hash_of_its_slot() {
+        let expected = keccak_slot(&word(3)) + U256::from(5 * 2);
+        assert_eq!(layout("3/array(2)").slot(U256::from(5)), expected);
+    }
+
+    #[test]
+    fn offset_moves_to_a_field_of_the_struct() {
+        let entry = StorageLayout::default().slot(U256::from(5));
+        assert_eq!(
+            layout("1/mapping(id)/offset(4)").slot(U256::from(5)),
+            entry + U256::from(4)
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for written in ["1/mapping(id)", "2/mapping(7)/array(3)/offset(1)"] {
+            assert_eq!(layout(written).to_string(), written);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |layout: &str| layout.parse::<StorageLayout>().unwrap_err();
+        assert_eq!(
+            error("mapping(id)"),
+            "the layout must start with the base slot, e.g. 1/mapping(id)"
+        );
+        assert_eq!(
+            error("1/mapping"),
+            "step \"mapping\" must be written as name(argument)"
+        );
+        assert_eq!(error("1/array(two)"), "step \"array(two)\" needs a number");
+        assert!(error("1/vector(id)").starts_with("Unknown step \"vector(id)\""));
+        assert_eq!(
+            error("1/offset(2)"),
+            "the layout must use the message id in a mapping(id) or array step"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Serves the API over a Unix domain socket, for sidecars that talk to it without a TCP port.
+
+use crate::server::{drain, log_accept_error, serve_connection, shutdown_signal, ServerConfig};
+use axum::Router;
+use hyper_util::server::graceful::GracefulShutdown;
+use std::io;
+use std::path::Path;
+use tokio::net::{UnixListener, UnixStream};
+
+/// Serves `app` on the socket at `path` until SIGINT or SIGTERM, then removes the socket file and drains the open
+/// connections.
+pub async fn serve(path: &Path, app: Router, config: ServerConfig) -> io::Result<()> {
+    if path.exists() {
+        // A socket file nobody accepts on is left over from a process that didn't shut down cleanly.
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is in use by another process", path.display()),
+            ));
+        }
+        tracing::warn!("⚠️ Removing stale socket file {}", path.display());
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+
+    let connections = GracefulShutdown::new();
+    let result = tokio::select! {
+        _ = accept_loop(listener, app, config, &connections) => Ok(()),
+        result = shutdown_signal() => result,
+    };
+    if let Err(err) = std::fs::remove_file(path) {
+        tracing::error!("❌ Cannot remove socket file {}: {:?}", path.display(), err);
+    }
+    drain(connections, config.shutdown_grace).await;
+    result
+}
+
+async fn accept_loop(
+    listener: UnixListener,
+    app: Router,
+    config: ServerConfig,
+    connections: &GracefulShutdown,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => serve_connection(stream, app.clone(), config, None, connections),
+            Err(err) => log_accept_error(err).await,
+        }
+    }
+}
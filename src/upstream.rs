@@ -0,0 +1,54 @@
+//! Records which upstream hosts served a request, for the opt-in `X-Upstream` response header that tells apart the
+//! configured nodes and providers when debugging a response.
+
+use axum::extract::{Request, State};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+pub const UPSTREAM_HEADER: &str = "x-upstream";
+
+tokio::task_local! {
+    static UPSTREAMS: RefCell<BTreeSet<String>>;
+}
+
+/// Returns the hostname of `url`, leaving out any credentials, path or query it carries.
+pub fn host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+}
+
+/// Notes the host of `url` as having served the current request, outside a tracked request doing nothing.
+pub fn record(url: &str) {
+    if let Some(host) = host(url) {
+        record_host(&host);
+    }
+}
+
+pub fn record_host(host: &str) {
+    let _ = UPSTREAMS.try_with(|upstreams| upstreams.borrow_mut().insert(host.to_owned()));
+}
+
+/// Lists the upstream hosts the request was served from in `X-Upstream` when `expose` is set, by hostname only so
+/// credentials or API keys in the configured URLs are never exposed.
+pub async fn expose(State(expose): State<bool>, request: Request, next: Next) -> Response {
+    if !expose {
+        return next.run(request).await;
+    }
+    let (upstreams, mut response) = UPSTREAMS
+        .scope(RefCell::default(), async {
+            let response = next.run(request).await;
+            (UPSTREAMS.with(RefCell::take), response)
+        })
+        .await;
+    if !upstreams.is_empty() {
+        let upstreams = upstreams.into_iter().collect::<Vec<_>>().join(", ");
+        if let Ok(value) = HeaderValue::from_str(&upstreams) {
+            response.headers_mut().insert(UPSTREAM_HEADER, value);
+        }
+    }
+    response
+}
@@ -0,0 +1,154 @@
+//! zstd compression of proof responses with a dictionary trained on typical proofs, which shrinks them far more than
+//! generic compression does. Clients opt in by fetching the dictionary and naming it in `X-Zstd-Dictionary`, everyone
+//! else gets the regular compression.
+
+use crate::endpoints::Endpoint;
+use crate::AppState;
+use alloy_primitives::hex;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, MethodRouter};
+use sp_io::hashing::sha2_256;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use zstd::bulk::Compressor;
+use zstd::dict::EncoderDictionary;
+
+/// Names the dictionary a client holds on requests, and the one a response was compressed with.
+pub const DICTIONARY_HEADER: &str = "x-zstd-dictionary";
+/// Advertises the dictionary on proof responses that weren't compressed with it.
+pub const DICTIONARY_AVAILABLE_HEADER: &str = "x-zstd-dictionary-available";
+pub const DICTIONARY_PATH: &str = "/compression/dictionary";
+/// Routes whose responses are compressed with the dictionary.
+const PROOF_PATH_PREFIXES: [&str; 3] = ["/eth/proof/", "/avl/proof/", "/message/"];
+
+pub struct ZstdDictionary {
+    /// Hex SHA-256 of the dictionary, which clients check the downloaded dictionary against.
+    id: String,
+    raw: Vec<u8>,
+    prepared: EncoderDictionary<'static>,
+}
+
+impl ZstdDictionary {
+    /// Loads a dictionary as written by `zstd --train`, prepared once for compression at `level`.
+    pub fn load(path: &Path, level: i32) -> io::Result<Self> {
+        let raw = std::fs::read(path)?;
+        Ok(ZstdDictionary {
+            id: hex::encode(sha2_256(&raw)),
+            prepared: EncoderDictionary::copy(&raw, level),
+            raw,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        Compressor::with_prepared_dictionary(&self.prepared)?.compress(data)
+    }
+
+    /// Whether the client accepts zstd and holds this dictionary.
+    fn accepted_by(&self, headers: &HeaderMap) -> bool {
+        let accepts_zstd = headers
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|coding| {
+                let mut params = coding.split(';').map(str::trim);
+                params.next() == Some("zstd")
+                    && !params.any(|param| {
+                        param.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0)
+                    })
+            });
+        accepts_zstd
+            && headers
+                .get(DICTIONARY_HEADER)
+                .is_some_and(|id| id.as_bytes() == self.id.as_bytes())
+    }
+}
+
+/// Compresses successful proof responses with the dictionary for clients holding it, leaving every other response to
+/// the regular compression.
+pub async fn compress(
+    State(dictionary): State<Option<Arc<ZstdDictionary>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(dictionary) = dictionary else {
+        return next.run(request).await;
+    };
+    let is_proof = PROOF_PATH_PREFIXES
+        .iter()
+        .any(|prefix| request.uri().path().starts_with(prefix));
+    if !is_proof {
+        return next.run(request).await;
+    }
+    let use_dictionary = dictionary.accepted_by(request.headers());
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.append(VARY, HeaderValue::from_static(DICTIONARY_HEADER));
+    if let Ok(id) = HeaderValue::from_str(dictionary.id()) {
+        headers.insert(DICTIONARY_AVAILABLE_HEADER, id);
+    }
+    if !use_dictionary || response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::error!("❌ Cannot read response body: {:?}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    match dictionary.compress(&bytes) {
+        Ok(compressed) => {
+            parts.headers.remove(CONTENT_LENGTH);
+            parts.headers.remove(DICTIONARY_AVAILABLE_HEADER);
+            parts
+                .headers
+                .insert(CONTENT_ENCODING, HeaderValue::from_static("zstd"));
+            parts
+                .headers
+                .append(VARY, HeaderValue::from_static("accept-encoding"));
+            if let Ok(id) = HeaderValue::from_str(dictionary.id()) {
+                parts.headers.insert(DICTIONARY_HEADER, id);
+            }
+            Response::from_parts(parts, Body::from(compressed))
+        }
+        Err(err) => {
+            tracing::warn!("⚠️ Cannot compress with the zstd dictionary: {:?}", err);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+/// Route serving the dictionary itself, for clients to fetch before opting in.
+pub fn route(dictionary: Arc<ZstdDictionary>) -> (Endpoint, MethodRouter<Arc<AppState>>) {
+    (
+        Endpoint {
+            path: DICTIONARY_PATH,
+            query_params: &[],
+            description: "Returns the zstd dictionary proof responses can be compressed with",
+        },
+        get(move || async move {
+            (
+                StatusCode::OK,
+                [
+                    ("Cache-Control", "public, max-age=300, must-revalidate"),
+                    (DICTIONARY_HEADER, dictionary.id()),
+                ],
+                dictionary.raw.clone(),
+            )
+                .into_response()
+        }),
+    )
+}